@@ -1,8 +1,14 @@
-use std::fs::read_to_string;
+use std::{fs::read_to_string, time::Duration};
 
 use serde::Deserialize;
 
-use crate::types::Point;
+use crate::{
+    definitions::{PROCESS_5E_NAME, PROCESS_NAME},
+    policy::{PauseUntil, Schedule},
+    process::ProcessMatcher,
+    state::State,
+    types::{Geometry, MouseButton, Point},
+};
 
 fn default_long_sleep() -> u64 {
     10
@@ -113,6 +119,760 @@ impl Default for ObsIntegration {
     }
 }
 
+#[cfg(feature = "gsi")]
+#[derive(Clone, Debug, Deserialize)]
+pub struct GsiConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "GsiConfig::default_host")]
+    host: String,
+    #[serde(default = "GsiConfig::default_port")]
+    port: u16,
+    token: Option<String>,
+}
+
+#[cfg(feature = "gsi")]
+impl GsiConfig {
+    fn default_host() -> String {
+        "127.0.0.1".to_string()
+    }
+
+    fn default_port() -> u16 {
+        3000
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+}
+
+#[cfg(feature = "gsi")]
+impl Default for GsiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: Self::default_host(),
+            port: Self::default_port(),
+            token: None,
+        }
+    }
+}
+
+#[cfg(feature = "wayland")]
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScreenCastConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(
+        rename = "token-file",
+        default = "ScreenCastConfig::default_token_file"
+    )]
+    token_file: String,
+}
+
+#[cfg(feature = "wayland")]
+impl ScreenCastConfig {
+    fn default_token_file() -> String {
+        "screencast.token".to_string()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn token_file(&self) -> &str {
+        &self.token_file
+    }
+}
+
+#[cfg(feature = "wayland")]
+impl Default for ScreenCastConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token_file: Self::default_token_file(),
+        }
+    }
+}
+
+/// Tail CS2's `console.log` (launch option `-condebug`) to react to matchmaking at once
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConsoleLogConfig {
+    #[serde(default)]
+    enabled: bool,
+    path: Option<String>,
+    #[serde(
+        rename = "match-found",
+        default = "ConsoleLogConfig::default_match_found"
+    )]
+    match_found: Vec<String>,
+    #[serde(default = "ConsoleLogConfig::default_connecting")]
+    connecting: Vec<String>,
+}
+
+impl ConsoleLogConfig {
+    fn default_match_found() -> Vec<String> {
+        vec![
+            "PopupAcceptMatchFound: OnShow".to_string(),
+            "popups/popup_accept_match.xml".to_string(),
+        ]
+    }
+
+    fn default_connecting() -> Vec<String> {
+        vec![
+            "PopupAcceptMatchFound: all players accepted".to_string(),
+            "CSGO_GAME_UI_STATE_LOADINGSCREEN".to_string(),
+            "Connecting to ".to_string(),
+        ]
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn match_found(&self) -> &[String] {
+        &self.match_found
+    }
+
+    pub fn connecting(&self) -> &[String] {
+        &self.connecting
+    }
+}
+
+impl Default for ConsoleLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            match_found: Self::default_match_found(),
+            connecting: Self::default_connecting(),
+        }
+    }
+}
+
+/// How the target processes are recognised, any matcher in the list is enough
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProcessConfig {
+    #[serde(default = "ProcessConfig::default_cs2")]
+    cs2: Vec<ProcessMatcher>,
+    #[serde(rename = "5e", default = "ProcessConfig::default_5e")]
+    e5: Vec<ProcessMatcher>,
+}
+
+impl ProcessConfig {
+    fn default_cs2() -> Vec<ProcessMatcher> {
+        vec![ProcessMatcher::name(PROCESS_NAME)]
+    }
+
+    // Under Wine/Proton the process may be reported as `wine64-preloader`, the exe is argv[0]
+    fn default_5e() -> Vec<ProcessMatcher> {
+        vec![
+            ProcessMatcher::name(PROCESS_5E_NAME),
+            ProcessMatcher::argv0_glob(&format!("*{PROCESS_5E_NAME}")),
+        ]
+    }
+
+    pub fn cs2(&self) -> &[ProcessMatcher] {
+        &self.cs2
+    }
+
+    pub fn e5(&self) -> &[ProcessMatcher] {
+        &self.e5
+    }
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            cs2: Self::default_cs2(),
+            e5: Self::default_5e(),
+        }
+    }
+}
+
+/// Only click when the target window has focus
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RequireForeground {
+    #[serde(default)]
+    cs2: bool,
+    #[serde(rename = "5e", default = "RequireForeground::default_5e")]
+    e5: bool,
+}
+
+impl RequireForeground {
+    fn default_5e() -> bool {
+        true
+    }
+
+    pub fn cs2(&self) -> bool {
+        self.cs2
+    }
+
+    pub fn e5(&self) -> bool {
+        self.e5
+    }
+}
+
+impl Default for RequireForeground {
+    fn default() -> Self {
+        Self {
+            cs2: false,
+            e5: Self::default_5e(),
+        }
+    }
+}
+
+/// Raise and focus the target window before clicking
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ActivateWindow {
+    #[serde(default)]
+    enabled: bool,
+    /// Give focus back to the previous window after clicking
+    #[serde(rename = "restore-focus", default)]
+    restore_focus: bool,
+    #[serde(rename = "timeout-ms", default = "ActivateWindow::default_timeout_ms")]
+    timeout_ms: u64,
+}
+
+impl ActivateWindow {
+    fn default_timeout_ms() -> u64 {
+        1000
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn restore_focus(&self) -> bool {
+        self.restore_focus
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+impl Default for ActivateWindow {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            restore_focus: false,
+            timeout_ms: Self::default_timeout_ms(),
+        }
+    }
+}
+
+/// Longest `hold-ms`, the accept countdown keeps running while the button is down
+const MAX_HOLD_MS: u64 = 2000;
+
+#[derive(Deserialize)]
+struct RawClickConfig {
+    #[serde(default = "ClickConfig::default_count")]
+    count: u32,
+    /// Wait after moving the cursor, before the first click
+    #[serde(
+        rename = "move-delay-ms",
+        default = "ClickConfig::default_move_delay_ms"
+    )]
+    move_delay_ms: u64,
+    /// Wait between clicks
+    #[serde(rename = "interval-ms", default = "ClickConfig::default_interval_ms")]
+    interval_ms: u64,
+    #[serde(default)]
+    button: MouseButton,
+    /// Keep the button pressed this long instead of a plain click
+    #[serde(rename = "hold-ms")]
+    hold_ms: Option<u64>,
+    /// Added to the detected point
+    #[serde(default)]
+    offset: [i32; 2],
+    /// Move the cursor back to where it was before clicking
+    #[serde(rename = "restore-cursor", default)]
+    restore_cursor: bool,
+}
+
+/// What one accept click does, see `platform_impl::ClickSequence`
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(try_from = "RawClickConfig")]
+pub struct ClickConfig {
+    count: u32,
+    move_delay_ms: u64,
+    interval_ms: u64,
+    button: MouseButton,
+    hold_ms: Option<u64>,
+    offset: [i32; 2],
+    restore_cursor: bool,
+}
+
+impl TryFrom<RawClickConfig> for ClickConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawClickConfig) -> Result<Self, Self::Error> {
+        if raw.count == 0 {
+            return Err(anyhow::anyhow!("[click] count must be at least 1"));
+        }
+        if let Some(hold) = raw.hold_ms.filter(|hold| !(1..=MAX_HOLD_MS).contains(hold)) {
+            return Err(anyhow::anyhow!(
+                "[click] hold-ms {hold} is out of range, 1 to {MAX_HOLD_MS}"
+            ));
+        }
+        Ok(Self {
+            count: raw.count,
+            move_delay_ms: raw.move_delay_ms,
+            interval_ms: raw.interval_ms,
+            button: raw.button,
+            hold_ms: raw.hold_ms,
+            offset: raw.offset,
+            restore_cursor: raw.restore_cursor,
+        })
+    }
+}
+
+impl ClickConfig {
+    fn default_count() -> u32 {
+        2
+    }
+
+    fn default_move_delay_ms() -> u64 {
+        50
+    }
+
+    fn default_interval_ms() -> u64 {
+        1000
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Same settings with a single click, for toggle buttons like GO
+    pub fn once(self) -> Self {
+        Self { count: 1, ..self }
+    }
+
+    pub fn move_delay(&self) -> Duration {
+        Duration::from_millis(self.move_delay_ms)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    pub fn button(&self) -> MouseButton {
+        self.button
+    }
+
+    pub fn hold(&self) -> Option<Duration> {
+        self.hold_ms.map(Duration::from_millis)
+    }
+
+    pub fn offset(&self) -> [i32; 2] {
+        self.offset
+    }
+
+    pub fn restore_cursor(&self) -> bool {
+        self.restore_cursor
+    }
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self {
+            count: Self::default_count(),
+            move_delay_ms: Self::default_move_delay_ms(),
+            interval_ms: Self::default_interval_ms(),
+            button: MouseButton::default(),
+            hold_ms: None,
+            offset: [0, 0],
+            restore_cursor: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InputKind {
+    /// enigo on Linux, `SendInput` on Windows
+    #[default]
+    Auto,
+    Enigo,
+    Xtest,
+    /// Virtual pointer device, requires "uinput" feature and write access to `/dev/uinput`
+    Uinput,
+}
+
+/// Which backend sends mouse input, see `platform_impl::backend`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct InputConfig {
+    #[serde(default)]
+    backend: InputKind,
+    /// X11 display for the xtest backend, `DISPLAY` is used if not set
+    #[cfg(not(windows))]
+    display: Option<String>,
+}
+
+impl InputConfig {
+    pub fn backend(&self) -> InputKind {
+        self.backend
+    }
+
+    #[cfg(not(windows))]
+    pub fn display(&self) -> Option<&str> {
+        self.display.as_deref()
+    }
+}
+
+/// Don't take the cursor away while the user is moving it
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct MouseGuard {
+    #[serde(default = "MouseGuard::default_enabled")]
+    enabled: bool,
+    /// Cursor is watched this long before each click
+    #[serde(rename = "sample-ms", default = "MouseGuard::default_sample_ms")]
+    sample_ms: u64,
+    /// Movement up to this many pixels is not counted
+    #[serde(default = "MouseGuard::default_threshold")]
+    threshold: i32,
+    /// Keep waiting this long for the mouse to stop, then give up until the next detection
+    #[serde(rename = "defer-ms", default = "MouseGuard::default_defer_ms")]
+    defer_ms: u64,
+    /// Send a desktop notification when the click is deferred
+    #[serde(default)]
+    notify: bool,
+}
+
+impl MouseGuard {
+    fn default_enabled() -> bool {
+        false
+    }
+
+    fn default_sample_ms() -> u64 {
+        200
+    }
+
+    fn default_threshold() -> i32 {
+        3
+    }
+
+    fn default_defer_ms() -> u64 {
+        3000
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn sample(&self) -> Duration {
+        Duration::from_millis(self.sample_ms)
+    }
+
+    pub fn threshold(&self) -> i32 {
+        self.threshold
+    }
+
+    pub fn defer(&self) -> Duration {
+        Duration::from_millis(self.defer_ms)
+    }
+
+    pub fn notify(&self) -> bool {
+        self.notify
+    }
+}
+
+impl Default for MouseGuard {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            sample_ms: Self::default_sample_ms(),
+            threshold: Self::default_threshold(),
+            defer_ms: Self::default_defer_ms(),
+            notify: false,
+        }
+    }
+}
+
+/// Measure how long the queue took and how fast the match was accepted
+#[derive(Clone, Debug, Deserialize)]
+pub struct QueueTimeConfig {
+    #[serde(default = "QueueTimeConfig::default_enabled")]
+    enabled: bool,
+    /// CSV file every match pop is appended to, relative to the config file, empty to keep
+    /// no history
+    #[serde(default = "QueueTimeConfig::default_history")]
+    history: String,
+}
+
+impl QueueTimeConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_history() -> String {
+        "queue-history.csv".to_string()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn history(&self) -> Option<&str> {
+        Some(self.history.as_str()).filter(|path| !path.is_empty())
+    }
+}
+
+impl Default for QueueTimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            history: Self::default_history(),
+        }
+    }
+}
+
+/// Desktop notification on entering a detection state
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotifyConfig {
+    /// State names as logged, e.g. `["MatchFound", "InGame"]`, empty to notify on nothing
+    #[serde(default)]
+    states: Vec<State>,
+}
+
+impl NotifyConfig {
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+}
+
+/// Read the lobby search timer, for a search that started before this program
+#[derive(Clone, Debug, Deserialize)]
+pub struct SearchTimerConfig {
+    #[serde(default)]
+    enabled: bool,
+    /// Directory of the glyph bitmaps written by `search-timer extract`
+    #[serde(default = "SearchTimerConfig::default_digits")]
+    digits: String,
+    #[serde(default = "Geometry::default_cs2_timer")]
+    region: Geometry,
+}
+
+impl SearchTimerConfig {
+    fn default_digits() -> String {
+        "digits".to_string()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn digits(&self) -> &str {
+        &self.digits
+    }
+
+    pub fn region(&self) -> Geometry {
+        self.region
+    }
+}
+
+impl Default for SearchTimerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            digits: Self::default_digits(),
+            region: Geometry::default_cs2_timer(),
+        }
+    }
+}
+
+/// Click Reconnect after the game crashed or disconnected during a match
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ReconnectConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "Geometry::default_cs2_reconnect")]
+    region: Geometry,
+}
+
+impl ReconnectConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn region(&self) -> Geometry {
+        self.region
+    }
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            region: Geometry::default_cs2_reconnect(),
+        }
+    }
+}
+
+/// Back in the lobby after a match failed because someone did not accept
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RequeueConfig {
+    #[serde(default)]
+    enabled: bool,
+    /// Start matchmaking again with the GO button
+    #[serde(rename = "click-go", default)]
+    click_go: bool,
+    #[serde(rename = "failed-region", default = "Geometry::default_cs2_failed")]
+    failed_region: Geometry,
+    #[serde(rename = "go-region", default = "Geometry::default_cs2_go")]
+    go_region: Geometry,
+}
+
+impl RequeueConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn click_go(&self) -> bool {
+        self.click_go
+    }
+
+    pub fn failed_region(&self) -> Geometry {
+        self.failed_region
+    }
+
+    pub fn go_region(&self) -> Geometry {
+        self.go_region
+    }
+}
+
+impl Default for RequeueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            click_go: false,
+            failed_region: Geometry::default_cs2_failed(),
+            go_region: Geometry::default_cs2_go(),
+        }
+    }
+}
+
+/// Stop accepting for `minutes` after every `after` accepts
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Cooldown {
+    after: u32,
+    minutes: u64,
+}
+
+impl Cooldown {
+    pub fn after(&self) -> u32 {
+        self.after
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.minutes * 60)
+    }
+}
+
+/// Rules that can block an accept, no rule means always accept
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PolicyConfig {
+    /// IANA name like "Europe/Berlin", the system time zone if not set
+    timezone: Option<String>,
+    /// Accept only inside one of these, empty means any time
+    #[serde(default)]
+    schedule: Vec<Schedule>,
+    #[serde(rename = "max-accepts")]
+    max_accepts: Option<u32>,
+    cooldown: Option<Cooldown>,
+    #[serde(rename = "pause-until")]
+    pause_until: Option<PauseUntil>,
+}
+
+impl PolicyConfig {
+    pub fn timezone(&self) -> Option<&str> {
+        self.timezone.as_deref()
+    }
+
+    pub fn schedule(&self) -> &[Schedule] {
+        &self.schedule
+    }
+
+    pub fn max_accepts(&self) -> Option<u32> {
+        self.max_accepts
+    }
+
+    pub fn cooldown(&self) -> Option<Cooldown> {
+        self.cooldown
+    }
+
+    pub fn pause_until(&self) -> Option<&PauseUntil> {
+        self.pause_until.as_ref()
+    }
+}
+
+/// Accept button regions, used when no absolute point is configured
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct GeometryConfig {
+    #[serde(default = "Geometry::default_cs2")]
+    cs2: Geometry,
+    #[serde(rename = "5e", default = "Geometry::default_5e")]
+    e5: Geometry,
+}
+
+impl GeometryConfig {
+    pub fn cs2(&self) -> Geometry {
+        self.cs2
+    }
+
+    pub fn e5(&self) -> Geometry {
+        self.e5
+    }
+}
+
+impl Default for GeometryConfig {
+    fn default() -> Self {
+        Self {
+            cs2: Geometry::default_cs2(),
+            e5: Geometry::default_5e(),
+        }
+    }
+}
+
+/// Where to read `cs2_video.txt` from, see `video_config::resolve`
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct VideoLocator {
+    #[serde(default)]
+    enabled: bool,
+    path: Option<String>,
+}
+
+impl VideoLocator {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+}
+
+/// CS2 gives 20 seconds to accept, leave time for the click itself
+const MAX_ACCEPT_DELAY: Duration = Duration::from_secs(15);
+
 fn default_5e_title() -> String {
     String::from_utf8(vec![
         53, 69, 229, 175, 185, 230, 136, 152, 229, 185, 179, 229, 143, 176,
@@ -129,9 +889,49 @@ pub struct Configure {
     e5_title: String,
     #[serde(default)]
     interval: Interval,
+    #[serde(default)]
+    geometry: GeometryConfig,
+    #[serde(rename = "cs2-video", default)]
+    cs2_video: VideoLocator,
+    #[serde(rename = "window-capture", default)]
+    window_capture: bool,
+    #[serde(rename = "accept-delay", default)]
+    accept_delay: u64,
+    #[serde(default)]
+    process: ProcessConfig,
+    #[serde(rename = "require-foreground", default)]
+    require_foreground: RequireForeground,
+    #[serde(rename = "activate-window", default)]
+    activate_window: ActivateWindow,
+    #[serde(default)]
+    click: ClickConfig,
+    #[serde(default)]
+    input: InputConfig,
+    #[serde(rename = "mouse-guard", default)]
+    mouse_guard: MouseGuard,
+    #[serde(rename = "console-log", default)]
+    console_log: ConsoleLogConfig,
+    #[serde(default)]
+    policy: PolicyConfig,
+    #[serde(default)]
+    requeue: RequeueConfig,
+    #[serde(default)]
+    reconnect: ReconnectConfig,
+    #[serde(rename = "queue-time", default)]
+    queue_time: QueueTimeConfig,
+    #[serde(default)]
+    notify: NotifyConfig,
+    #[serde(rename = "search-timer", default)]
+    search_timer: SearchTimerConfig,
     #[cfg(feature = "obs")]
     #[serde(default)]
     obs: ObsIntegration,
+    #[cfg(feature = "wayland")]
+    #[serde(default)]
+    screencast: ScreenCastConfig,
+    #[cfg(feature = "gsi")]
+    #[serde(default)]
+    gsi: GsiConfig,
 }
 
 impl Configure {
@@ -151,12 +951,113 @@ impl Configure {
         self.interval
     }
 
+    pub fn geometry(&self) -> GeometryConfig {
+        self.geometry
+    }
+
+    pub fn cs2_video(&self) -> &VideoLocator {
+        &self.cs2_video
+    }
+
+    pub fn window_capture(&self) -> bool {
+        self.window_capture
+    }
+
+    /// Capped by `MAX_ACCEPT_DELAY`, CS2 drops the match if nobody accepts in time
+    pub fn accept_delay(&self) -> Duration {
+        Duration::from_secs(self.accept_delay).min(MAX_ACCEPT_DELAY)
+    }
+
+    pub fn process(&self) -> &ProcessConfig {
+        &self.process
+    }
+
+    pub fn require_foreground(&self) -> RequireForeground {
+        self.require_foreground
+    }
+
+    pub fn activate_window(&self) -> ActivateWindow {
+        self.activate_window
+    }
+
+    pub fn click(&self) -> ClickConfig {
+        self.click
+    }
+
+    pub fn input(&self) -> &InputConfig {
+        &self.input
+    }
+
+    pub fn mouse_guard(&self) -> MouseGuard {
+        self.mouse_guard
+    }
+
+    pub fn console_log(&self) -> &ConsoleLogConfig {
+        &self.console_log
+    }
+
+    pub fn policy(&self) -> &PolicyConfig {
+        &self.policy
+    }
+
+    pub fn requeue(&self) -> RequeueConfig {
+        self.requeue
+    }
+
+    pub fn reconnect(&self) -> ReconnectConfig {
+        self.reconnect
+    }
+
+    pub fn queue_time(&self) -> &QueueTimeConfig {
+        &self.queue_time
+    }
+
+    pub fn notify(&self) -> &NotifyConfig {
+        &self.notify
+    }
+
+    pub fn search_timer(&self) -> &SearchTimerConfig {
+        &self.search_timer
+    }
+
     #[cfg(feature = "obs")]
     pub fn obs(&self) -> &ObsIntegration {
         &self.obs
     }
 
+    #[cfg(feature = "gsi")]
+    pub fn gsi(&self) -> &GsiConfig {
+        &self.gsi
+    }
+
+    #[cfg(feature = "wayland")]
+    pub fn screencast(&self) -> &ScreenCastConfig {
+        &self.screencast
+    }
+
     pub fn e5_title(&self) -> &str {
         &self.e5_title
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn click(text: &str) -> anyhow::Result<ClickConfig> {
+        Ok(toml::from_str(text)?)
+    }
+
+    #[test]
+    fn click_limits() {
+        let config = click("count = 1\nhold-ms = 2000").unwrap();
+        assert_eq!(config.count(), 1);
+        assert_eq!(config.hold(), Some(Duration::from_secs(2)));
+        assert_eq!(click("").unwrap().count(), 2);
+
+        assert!(click("count = 0").is_err());
+        assert!(click("hold-ms = 0").is_err());
+        assert!(click("hold-ms = 2001").is_err());
+        assert!(toml::from_str::<Configure>("[click]\ncount = 0").is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Tail CS2's `console.log`, written when the game is started with `-condebug`.
+//!
+//! Matchmaking lines show up there before the accept button is drawn, so the main loop
+//! can stop sleeping and scan right away.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+
+use crate::configure::ConsoleLogConfig;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Events older than this are dropped, the popup is long gone
+const EXPIRE_AFTER: Duration = Duration::from_secs(20);
+
+static PENDING: Mutex<Option<(ConsoleEvent, Instant)>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ConsoleEvent {
+    MatchFound,
+    /// Match is accepted by everyone, the game is loading the map
+    Connecting,
+}
+
+/// Whether an event is waiting for the main loop, does not consume it
+pub(crate) fn pending() -> bool {
+    PENDING
+        .lock()
+        .unwrap()
+        .is_some_and(|(_, at)| at.elapsed() < EXPIRE_AFTER)
+}
+
+pub(crate) fn take() -> Option<ConsoleEvent> {
+    PENDING
+        .lock()
+        .unwrap()
+        .take()
+        .filter(|(_, at)| at.elapsed() < EXPIRE_AFTER)
+        .map(|(event, _)| event)
+}
+
+fn parse_line(line: &str, config: &ConsoleLogConfig) -> Option<ConsoleEvent> {
+    let contains = |patterns: &[String]| patterns.iter().any(|p| line.contains(p.as_str()));
+    if contains(config.match_found()) {
+        Some(ConsoleEvent::MatchFound)
+    } else if contains(config.connecting()) {
+        Some(ConsoleEvent::Connecting)
+    } else {
+        None
+    }
+}
+
+/// Volume and file index, changes when the file is replaced by a new one
+#[cfg(windows)]
+fn file_id(file: &File) -> anyhow::Result<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+
+    // Borrowed from `file`, `HFILE` does not close it
+    let handle = unsafe { winsafe::HFILE::from_ptr(file.as_raw_handle()) };
+    let info = handle
+        .GetFileInformationByHandle()
+        .context("GetFileInformationByHandle")?;
+    Ok((info.dwVolumeSerialNumber as u64, info.nFileIndex()))
+}
+
+/// Device and inode, changes when the file is replaced by a new one
+#[cfg(not(windows))]
+fn file_id(file: &File) -> anyhow::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = file.metadata()?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+/// Follows a file across truncation and rotation, returns complete lines only
+struct LogTail {
+    path: PathBuf,
+    file_id: Option<(u64, u64)>,
+    position: u64,
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    /// Start from the current end, history from previous sessions is skipped
+    fn new(path: PathBuf) -> Self {
+        let file = File::open(&path).ok();
+        Self {
+            file_id: file.as_ref().and_then(|file| file_id(file).ok()),
+            position: file
+                .and_then(|file| file.metadata().ok())
+                .map(|m| m.len())
+                .unwrap_or_default(),
+            path,
+            partial: vec![],
+        }
+    }
+
+    fn read_lines(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            // CS2 is not started with -condebug yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let metadata = file.metadata()?;
+
+        let id = file_id(&file)?;
+        if self.file_id != Some(id) || metadata.len() < self.position {
+            if self.file_id.is_some() {
+                log::debug!("{:?} is rotated or truncated, read from start", self.path);
+            }
+            self.file_id = Some(id);
+            self.position = 0;
+            self.partial.clear();
+        }
+        if metadata.len() == self.position {
+            return Ok(vec![]);
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let read = file
+            .take(metadata.len() - self.position)
+            .read_to_end(&mut self.partial)?;
+        self.position += read as u64;
+
+        let Some(end) = self.partial.iter().rposition(|b| *b == b'\n') else {
+            return Ok(vec![]);
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+fn resolve_path(config: &ConsoleLogConfig) -> anyhow::Result<PathBuf> {
+    match config.path() {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(crate::steam::find_cs2_install()
+            .context("CS2 install not found in Steam libraries, set console-log.path")?
+            .join("game")
+            .join("csgo")
+            .join("console.log")),
+    }
+}
+
+pub(crate) fn spawn(config: &ConsoleLogConfig) -> anyhow::Result<()> {
+    let path = resolve_path(config)?;
+    log::info!("Watching {path:?}");
+
+    let config = config.clone();
+    let mut tail = LogTail::new(path);
+    std::thread::Builder::new()
+        .name("console-log".into())
+        .spawn(move || {
+            loop {
+                let lines = tail
+                    .read_lines()
+                    .inspect_err(|e| log::warn!("Read console log error: {e:?}"))
+                    .unwrap_or_default();
+                for event in lines.iter().filter_map(|l| parse_line(l, &config)) {
+                    log::debug!("Console log event: {event:?}");
+                    PENDING.lock().unwrap().replace((event, Instant::now()));
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/console-log");
+
+    /// Empty scratch directory for one test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("console-log-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn append(path: &PathBuf, text: &str) {
+        use std::io::Write;
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    #[test]
+    fn match_log_events() {
+        let config = ConsoleLogConfig::default();
+        let log = std::fs::read_to_string(format!("{FIXTURES}/match.log")).unwrap();
+        let events = log
+            .lines()
+            .filter_map(|line| parse_line(line, &config))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                ConsoleEvent::MatchFound,
+                ConsoleEvent::MatchFound,
+                ConsoleEvent::Connecting,
+                ConsoleEvent::Connecting,
+                ConsoleEvent::Connecting,
+            ]
+        );
+    }
+
+    #[test]
+    fn tail_skips_history_and_waits_for_complete_lines() {
+        let path = temp_dir("history").join("console.log");
+        append(&path, "old session\n");
+        let mut tail = LogTail::new(path.clone());
+        assert!(tail.read_lines().unwrap().is_empty());
+
+        append(&path, "PopupAcceptMatchFound: On");
+        assert!(tail.read_lines().unwrap().is_empty());
+        append(&path, "Show\nsecond\n");
+        assert_eq!(
+            tail.read_lines().unwrap(),
+            ["PopupAcceptMatchFound: OnShow", "second"]
+        );
+    }
+
+    #[test]
+    fn tail_follows_truncation_and_rotation() {
+        let dir = temp_dir("rotation");
+        let path = dir.join("console.log");
+        let mut tail = LogTail::new(path.clone());
+        // Not written yet
+        assert!(tail.read_lines().unwrap().is_empty());
+
+        append(&path, "first session line\n");
+        assert_eq!(tail.read_lines().unwrap(), ["first session line"]);
+
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(tail.read_lines().unwrap(), ["new"]);
+
+        // A new file with the old length, e.g. the game started again
+        let rotated = dir.join("console.log.new");
+        std::fs::write(&rotated, "abc\n").unwrap();
+        std::fs::rename(&rotated, &path).unwrap();
+        assert_eq!(tail.read_lines().unwrap(), ["abc"]);
+    }
+}
@@ -1,6 +1,10 @@
+pub(crate) const CS2_WINDOW_TITLE: &str = "Counter-Strike 2";
+
 #[cfg(target_os = "linux")]
 mod linux {
     pub(crate) const PROCESS_NAME: &str = "cs2";
+    // Runs under Wine/Proton
+    pub(crate) const PROCESS_5E_NAME: &str = "5EClient.exe";
 }
 
 mod windows {
@@ -0,0 +1,349 @@
+//! CS2 Game State Integration listener.
+//!
+//! CS2 POSTs JSON to the URI in `gamestate_integration_*.cfg` whenever the game state changes,
+//! and at least once per heartbeat while it is running.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, anyhow};
+
+use crate::configure::{Configure, GsiConfig};
+
+/// No update for this long means CS2 stopped sending, the state is not trusted anymore
+const STALE_AFTER: Duration = Duration::from_secs(65);
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+const CFG_FILE_NAME: &str = "gamestate_integration_autoaccept.cfg";
+
+static LAST_STATE: Mutex<Option<(GameState, Instant)>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GameState {
+    Menu,
+    /// Back in the menu from a match that was not over, e.g. after a disconnect
+    Left,
+    Warmup,
+    Live,
+    Intermission,
+    GameOver,
+}
+
+impl GameState {
+    pub(crate) fn in_game(&self) -> bool {
+        !matches!(self, Self::Menu | Self::Left)
+    }
+
+    /// Leaving now would abandon the match, unlike after `GameOver`
+    pub(crate) fn mid_match(&self) -> bool {
+        matches!(self, Self::Warmup | Self::Live | Self::Intermission)
+    }
+
+    /// Read the state from a GSI payload
+    pub(crate) fn from_payload(payload: &serde_json::Value) -> Self {
+        match payload["map"]["phase"].as_str() {
+            Some("warmup") => return Self::Warmup,
+            Some("live") => return Self::Live,
+            Some("intermission") => return Self::Intermission,
+            Some("gameover") => return Self::GameOver,
+            _ => {}
+        }
+        match payload["player"]["activity"].as_str() {
+            Some("playing") | Some("textinput") if payload["map"].is_object() => Self::Live,
+            // The payload leaving a map still carries its last phase
+            _ if payload["previously"].is_object()
+                && Self::from_payload(&payload["previously"]).mid_match() =>
+            {
+                Self::Left
+            }
+            _ => Self::Menu,
+        }
+    }
+}
+
+/// Latest state reported by CS2, `None` if nothing was received recently
+pub(crate) fn current() -> Option<GameState> {
+    LAST_STATE
+        .lock()
+        .unwrap()
+        .filter(|(_, at)| at.elapsed() < STALE_AFTER)
+        .map(|(state, _)| state)
+}
+
+fn update(state: GameState) {
+    let mut last = LAST_STATE.lock().unwrap();
+    // Later payloads in the menu don't mention the left match anymore
+    let state = match *last {
+        Some((GameState::Left, _)) if state == GameState::Menu => GameState::Left,
+        _ => state,
+    };
+    if last.is_none_or(|(last, _)| last != state) {
+        log::debug!("GSI state: {state:?}");
+    }
+    last.replace((state, Instant::now()));
+}
+
+pub(crate) fn check_token(payload: &serde_json::Value, token: Option<&str>) -> bool {
+    match token {
+        Some(token) => payload["auth"]["token"].as_str() == Some(token),
+        None => true,
+    }
+}
+
+/// Read one HTTP request, returns its body
+fn read_request(stream: &mut TcpStream) -> anyhow::Result<String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("POST ") {
+        return Err(anyhow!("Unexpected request: {:?}", line.trim_end()));
+    }
+
+    let mut content_length = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(anyhow!("Connection closed in headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let length = content_length.context("Missing Content-Length")?;
+    if length > MAX_BODY_SIZE {
+        return Err(anyhow!("Body too large: {length}"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(String::from_utf8(body)?)
+}
+
+fn respond(stream: &mut TcpStream, status: &str) -> anyhow::Result<()> {
+    stream.write_all(
+        format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").as_bytes(),
+    )?;
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, token: Option<&str>) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let body = match read_request(&mut stream) {
+        Ok(body) => body,
+        Err(e) => {
+            respond(&mut stream, "400 Bad Request")?;
+            return Err(e);
+        }
+    };
+
+    let payload: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            respond(&mut stream, "400 Bad Request")?;
+            return Err(e.into());
+        }
+    };
+    if !check_token(&payload, token) {
+        respond(&mut stream, "401 Unauthorized")?;
+        return Err(anyhow!("GSI payload with wrong auth token"));
+    }
+
+    update(GameState::from_payload(&payload));
+    respond(&mut stream, "200 OK")
+}
+
+/// Start listening on the configured address, requests are handled one at a time
+pub(crate) fn spawn(config: &GsiConfig) -> anyhow::Result<()> {
+    let listener = TcpListener::bind((config.host(), config.port()))
+        .with_context(|| format!("Bind GSI listener {}:{}", config.host(), config.port()))?;
+    log::info!("GSI listening on {}:{}", config.host(), config.port());
+
+    let token = config.token().map(str::to_string);
+    std::thread::Builder::new()
+        .name("gsi-listener".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream.inspect_err(|e| log::warn!("GSI accept error: {e:?}"))
+                else {
+                    continue;
+                };
+                handle_connection(stream, token.as_deref())
+                    .inspect_err(|e| log::warn!("GSI request error: {e:#}"))
+                    .ok();
+            }
+        })?;
+    Ok(())
+}
+
+/// Random hex token from the OS generator, CS2 sends it back with every payload
+fn random_token() -> anyhow::Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| anyhow!("Read OS randomness: {e}"))?;
+    Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+fn cfg_path(cs2_dir: Option<&String>) -> anyhow::Result<PathBuf> {
+    let cs2_dir = match cs2_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::steam::find_cs2_install()
+            .context("CS2 install not found in Steam libraries, specify it with --cs2-dir")?,
+    };
+    let cfg_dir = cs2_dir.join("game").join("csgo").join("cfg");
+    if !cfg_dir.is_dir() {
+        return Err(anyhow!("{cfg_dir:?} is not a directory"));
+    }
+    Ok(cfg_dir.join(CFG_FILE_NAME))
+}
+
+fn render_cfg(host: &str, port: u16, token: &str) -> String {
+    format!(
+        r#""cs2-auto-accept"
+{{
+	"uri"		"http://{host}:{port}"
+	"timeout"	"5.0"
+	"buffer"	"0.1"
+	"throttle"	"0.5"
+	"heartbeat"	"30.0"
+	"auth"
+	{{
+		"token"	"{token}"
+	}}
+	"data"
+	{{
+		"provider"	"1"
+		"map"		"1"
+		"round"		"1"
+		"player_id"	"1"
+		"player_state"	"1"
+	}}
+}}
+"#
+    )
+}
+
+/// Enable the listener in the configure file and save the token, other content is kept as is
+fn save_token(config_file: &str, token: &str) -> anyhow::Result<()> {
+    let content = match std::fs::read_to_string(config_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Read {config_file}")),
+    };
+    let mut document = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("Parse {config_file}"))?;
+    document["gsi"]["enabled"] = toml_edit::value(true);
+    document["gsi"]["token"] = toml_edit::value(token);
+    std::fs::write(config_file, document.to_string())
+        .with_context(|| format!("Write {config_file}"))
+}
+
+/// Write `gamestate_integration_autoaccept.cfg` into the CS2 cfg directory
+pub(crate) fn install(config_file: &String, cs2_dir: Option<&String>) -> anyhow::Result<()> {
+    let config = Configure::load(config_file)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default();
+    let path = cfg_path(cs2_dir)?;
+    let token = random_token()?;
+
+    std::fs::write(
+        &path,
+        render_cfg(config.gsi().host(), config.gsi().port(), &token),
+    )
+    .with_context(|| format!("Write {path:?}"))?;
+    save_token(config_file, &token)?;
+    log::info!("Installed {path:?}, restart CS2 to load it");
+    Ok(())
+}
+
+pub(crate) fn uninstall(cs2_dir: Option<&String>) -> anyhow::Result<()> {
+    let path = cfg_path(cs2_dir)?;
+    if !path.exists() {
+        log::info!("{path:?} is not installed");
+        return Ok(());
+    }
+    std::fs::remove_file(&path).with_context(|| format!("Remove {path:?}"))?;
+    log::info!("Removed {path:?}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/gsi");
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    fn payload(name: &str) -> String {
+        std::fs::read_to_string(format!("{FIXTURES}/{name}")).unwrap()
+    }
+
+    /// POST `body` to a listener handling one request, returns the status line
+    fn post(body: &str, token: Option<&'static str>) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(stream, token)
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: {address}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        server.join().unwrap().ok();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn payload_state() {
+        let state = |name| GameState::from_payload(&serde_json::from_str(&payload(name)).unwrap());
+        assert_eq!(state("live.json"), GameState::Live);
+        assert_eq!(state("menu.json"), GameState::Menu);
+        assert_eq!(state("left.json"), GameState::Left);
+        let warmup = serde_json::json!({ "map": { "phase": "warmup" } });
+        assert_eq!(GameState::from_payload(&warmup), GameState::Warmup);
+        let game_over = serde_json::json!({
+            "player": { "activity": "menu" },
+            "previously": { "map": { "phase": "gameover" }, "player": { "activity": "playing" } },
+        });
+        assert_eq!(GameState::from_payload(&game_over), GameState::Menu);
+    }
+
+    // One test for everything that touches `LAST_STATE`
+    #[test]
+    fn listener() {
+        assert_eq!(post(&payload("live.json"), Some(TOKEN)), "HTTP/1.1 200 OK");
+        assert_eq!(current(), Some(GameState::Live));
+
+        let status = post(&payload("menu.json"), Some("another token"));
+        assert_eq!(status, "HTTP/1.1 401 Unauthorized");
+        assert_eq!(current(), Some(GameState::Live));
+
+        assert_eq!(post("{", None), "HTTP/1.1 400 Bad Request");
+        assert_eq!(post(&payload("menu.json"), None), "HTTP/1.1 200 OK");
+        assert_eq!(current(), Some(GameState::Menu));
+
+        // Heartbeats after leaving a match keep the state until the next map
+        assert_eq!(post(&payload("left.json"), None), "HTTP/1.1 200 OK");
+        assert_eq!(post(&payload("menu.json"), None), "HTTP/1.1 200 OK");
+        assert_eq!(current(), Some(GameState::Left));
+        assert_eq!(post(&payload("live.json"), None), "HTTP/1.1 200 OK");
+        assert_eq!(current(), Some(GameState::Live));
+    }
+}
@@ -6,33 +6,48 @@ use slint::{Color, Model};
 
 use crate::EXIT_SIGNAL;
 use crate::real_main_guarder;
+use crate::state::{self, StateEvent};
 use crate::tools::timestamp_fmt;
 
 slint::include_modules!();
 
 pub(crate) static SENDER: OnceLock<MessageHelper> = OnceLock::new();
+/// Does nothing until the window is up, e.g. in tests
 #[macro_export]
 macro_rules! update_status {
+    (@countdown $seconds:expr) => {
+        if let Some(sender) = $crate::gui::SENDER.get() {
+            sender.countdown($seconds);
+        }
+    };
+    (@queue $text:expr) => {
+        if let Some(sender) = $crate::gui::SENDER.get() {
+            sender.queue($text);
+        }
+    };
     ($x:expr, $y: expr) => {
-        gui::SENDER
-            .get()
-            .unwrap()
-            .point($x, $y);
+        if let Some(sender) = $crate::gui::SENDER.get() {
+            sender.point($x, $y);
+        }
     };
     ($($arg:tt)*) => {
-        gui::SENDER
-            .get()
-            .unwrap()
-            .log(format!(
+        if let Some(sender) = $crate::gui::SENDER.get() {
+            sender.log(format!(
                 "{} {}",
-                timestamp_fmt("%Y-%m-%d %H:%M:%S.%3f"),
+                $crate::tools::timestamp_fmt("%Y-%m-%d %H:%M:%S.%3f"),
                 format!($($arg)*)
             ));
+        }
     };
 }
 enum MessageEvent {
     Point(usize, usize),
     Log(String),
+    State(StateEvent),
+    /// Seconds left before the delayed click, `None` hides it
+    Countdown(Option<u64>),
+    /// Queue time of the running search or the last match
+    Queue(String),
     Exit,
 }
 
@@ -55,6 +70,18 @@ impl MessageHelper {
         self.inner.send(MessageEvent::Point(x, y)).ok()
     }
 
+    pub(crate) fn countdown(&self, seconds: Option<u64>) -> Option<()> {
+        self.inner.send(MessageEvent::Countdown(seconds)).ok()
+    }
+
+    pub(crate) fn queue(&self, text: String) -> Option<()> {
+        self.inner.send(MessageEvent::Queue(text)).ok()
+    }
+
+    fn state(&self, event: StateEvent) -> Option<()> {
+        self.inner.send(MessageEvent::State(event)).ok()
+    }
+
     fn exit(&self) -> Option<()> {
         self.inner.send(MessageEvent::Exit).ok()
     }
@@ -91,6 +118,26 @@ fn handle_event(
                     .upgrade_in_event_loop(move |w| w.set_last_status(s.into()))
                     .unwrap();
             }
+            MessageEvent::State(event) => {
+                window
+                    .upgrade_in_event_loop(move |w| {
+                        w.set_state(format!("{} (from {})", event.to(), event.from()).into())
+                    })
+                    .unwrap();
+            }
+            MessageEvent::Countdown(seconds) => {
+                let text = seconds
+                    .map(|s| format!("Accepting in {s}s"))
+                    .unwrap_or_default();
+                window
+                    .upgrade_in_event_loop(move |w| w.set_countdown(text.into()))
+                    .unwrap();
+            }
+            MessageEvent::Queue(text) => {
+                window
+                    .upgrade_in_event_loop(move |w| w.set_queue(text.into()))
+                    .unwrap();
+            }
             MessageEvent::Exit => break,
         };
 
@@ -106,6 +153,19 @@ pub(crate) fn gui_entry(config: &String, force_distance: bool) -> anyhow::Result
 
     SENDER.set(s.clone()).unwrap();
 
+    // Detached, lives as long as the process
+    std::thread::spawn({
+        let s = s.clone();
+        let events = state::subscribe();
+        move || {
+            for event in events {
+                if s.state(event).is_none() {
+                    break;
+                }
+            }
+        }
+    });
+
     let matcher = std::thread::spawn({
         let config = config.to_string();
         move || real_main_guarder(&config, force_distance)
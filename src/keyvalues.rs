@@ -0,0 +1,203 @@
+//! Minimal reader for Valve's KeyValues text format (`*.vdf`, `cs2_video.txt`).
+
+use anyhow::{anyhow, bail};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    String(String),
+    Section(Section),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Section {
+    entries: Vec<(String, Value)>,
+}
+
+impl Section {
+    /// Keys are case-insensitive in KeyValues, the first match wins.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    pub(crate) fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            Value::String(s) => Some(s),
+            Value::Section(_) => None,
+        }
+    }
+
+    pub(crate) fn get_section(&self, key: &str) -> Option<&Section> {
+        match self.get(key)? {
+            Value::Section(s) => Some(s),
+            Value::String(_) => None,
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+#[derive(Debug)]
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+fn tokenize(content: &str) -> anyhow::Result<Vec<Token>> {
+    let mut ret = vec![];
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => ret.push(Token::Open),
+            '}' => ret.push(Token::Close),
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(c) => s.push(c),
+                            None => bail!("Unexpected end of file in escape sequence"),
+                        },
+                        Some(c) => s.push(c),
+                        None => bail!("Unterminated string: {s:?}"),
+                    }
+                }
+                ret.push(Token::Str(s));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                // Unquoted token, terminated by whitespace or a brace
+                let mut s = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                ret.push(Token::Str(s));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn parse_section(tokens: &mut std::vec::IntoIter<Token>, nested: bool) -> anyhow::Result<Section> {
+    let mut section = Section::default();
+    loop {
+        let key = match tokens.next() {
+            Some(Token::Str(key)) => key,
+            Some(Token::Close) if nested => return Ok(section),
+            None if !nested => return Ok(section),
+            Some(Token::Close) => bail!("Unexpected '}}'"),
+            Some(Token::Open) => bail!("Unexpected '{{', expect key"),
+            None => bail!("Unexpected end of file, expect '}}'"),
+        };
+        let value = match tokens.next() {
+            Some(Token::Str(value)) => Value::String(value),
+            Some(Token::Open) => Value::Section(parse_section(tokens, true)?),
+            _ => return Err(anyhow!("Missing value for key {key:?}")),
+        };
+        section.entries.push((key, value));
+    }
+}
+
+/// Parse the whole file, the returned root section usually holds a single named section.
+pub(crate) fn parse(content: &str) -> anyhow::Result<Section> {
+    parse_section(&mut tokenize(content)?.into_iter(), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn quoting() {
+        let root =
+            parse(r#""key with space" "value with space" unquoted bare "" empty "{}" braces"#)
+                .unwrap();
+        assert_eq!(root.get_str("key with space"), Some("value with space"));
+        assert_eq!(root.get_str("unquoted"), Some("bare"));
+        assert_eq!(root.get_str(""), Some("empty"));
+        assert_eq!(root.get_str("{}"), Some("braces"));
+    }
+
+    #[test]
+    fn escapes() {
+        let root = parse(r#""a" "line\nnext\ttab" "b" "say \"hi\" \\ done""#).unwrap();
+        assert_eq!(root.get_str("a"), Some("line\nnext\ttab"));
+        assert_eq!(root.get_str("b"), Some(r#"say "hi" \ done"#));
+    }
+
+    #[test]
+    fn comments() {
+        let root = parse(
+            "// header comment\n\"a\" \"1\" // trailing comment\n// \"b\" \"2\"\n\"c\" \"http://x\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            root.iter().collect::<Vec<_>>(),
+            [("a", &string("1")), ("c", &string("http://x"))]
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let root = parse(
+            r#"
+            "Outer"
+            {
+                "Inner" { "Key" "value" }
+                "Empty" {}
+                "key" "first"
+                "KEY" "second"
+            }"#,
+        )
+        .unwrap();
+        let outer = root.get_section("outer").unwrap();
+        assert_eq!(
+            outer.get_section("inner").and_then(|s| s.get_str("key")),
+            Some("value")
+        );
+        assert_eq!(outer.get_section("Empty"), Some(&Section::default()));
+        // Case-insensitive, the first one wins
+        assert_eq!(outer.get_str("Key"), Some("first"));
+        assert_eq!(outer.get_str("Inner"), None);
+        assert_eq!(outer.get_section("key"), None);
+    }
+
+    #[test]
+    fn errors() {
+        for content in [
+            r#""a" "unterminated"#,
+            r#""a" "escape at end\"#,
+            r#""a" { "b" "c""#,
+            r#""a" "b" }"#,
+            r#"{ "a" "b" }"#,
+            r#""a""#,
+            r#""a" }"#,
+        ] {
+            assert!(parse(content).is_err(), "{content:?}");
+        }
+    }
+}
@@ -4,18 +4,36 @@
 )]
 
 mod configure;
+mod console_log;
 mod definitions;
+#[cfg(feature = "gsi")]
+mod gsi;
 #[cfg(feature = "gui-only")]
 mod gui;
+mod keyvalues;
 mod matcher;
 mod not_impl;
 #[cfg(feature = "obs")]
 mod obs;
 mod platform_impl;
+mod policy;
+mod process;
+mod queue_time;
+#[cfg(feature = "wayland")]
+mod screencast;
+mod search_timer;
+mod state;
+mod steam;
 mod target_5e;
 mod target_main;
+mod target_reconnect;
+mod target_requeue;
 mod tools;
 mod types;
+mod video_config;
+mod window;
+#[cfg(not(windows))]
+mod x11;
 
 use std::{
     sync::{OnceLock, atomic::AtomicBool, mpsc},
@@ -24,17 +42,23 @@ use std::{
 };
 
 use clap::{Command, arg, builder::PossibleValue};
-use configure::Configure;
-use image::{DynamicImage, ImageBuffer, Rgb};
+use configure::{Configure, NotifyConfig};
+use image::{DynamicImage, ImageBuffer, Rgb, RgbaImage};
 use rayon::iter::ParallelIterator;
-use sysinfo::{ProcessRefreshKind, RefreshKind};
-use tools::{continue_test_area, load_and_display, test_image, timestamp_fmt};
+use tools::{
+    continue_test_area, load_and_display, measure_process_overhead, test_image, timestamp_fmt,
+};
 use xcap::Monitor;
 
 use crate::{
+    definitions::CS2_WINDOW_TITLE,
     matcher::Matcher,
-    platform_impl::{get_pos, move_mouse_click},
-    types::{MatchOptions, Point, PointOption},
+    platform_impl::{InputBackend, get_pos, move_mouse_click, wait_mouse_idle},
+    policy::AcceptPolicy,
+    process::ProcessWatcher,
+    state::{Observation, State, StateMachine},
+    types::{Geometry, MatchOptions, Point, PointOption, Region, RegionError},
+    window::GameArea,
 };
 
 #[cfg(feature = "distance")]
@@ -49,7 +73,10 @@ use crate::not_impl::*;
 static DRY_RUN: AtomicBool = AtomicBool::new(false);
 static SAVE_IMAGE: AtomicBool = AtomicBool::new(false);
 static EXIT_SIGNAL: OnceLock<bool> = OnceLock::new();
+static CS2_VIDEO_CFG: OnceLock<String> = OnceLock::new();
 
+/// How long to keep scanning after the console log reports a match
+const FAST_SCAN_DURATION: Duration = Duration::from_secs(30);
 const X_LIMIT: usize = 10;
 const Y_LIMIT: usize = 8;
 const X_LIMIT_5E: usize = 26;
@@ -103,7 +130,7 @@ macro_rules! send_obs_command {
     };
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum SearchResult {
     Found(usize, usize),
     NotFound,
@@ -123,49 +150,83 @@ type SubImageType = Vec<u8>;
 type ImageType = ImageBuffer<BasicImageType, SubImageType>;
 
 fn determine_point(monitor: Monitor, is_5e: bool) -> anyhow::Result<Point> {
-    let x = monitor.x()?;
-    let y = monitor.y()? - if is_5e { -50 } else { 100 };
-    let height = monitor.height()? as i32;
-    let width = monitor.width()? as i32;
-    let h = 100;
-    let w = 200;
+    let geometry = if is_5e {
+        Geometry::default_5e()
+    } else {
+        Geometry::default_cs2()
+    };
+    Ok(geometry.region(Point::from_monitor(&monitor)?))
+}
 
-    let mid_x = x + width / 2;
-    let mid_y = y + height / 2;
+/// Check the accept regions fit in the primary monitor, retrying makes no sense if they don't
+fn validate_geometry(config: &Configure) -> anyhow::Result<()> {
+    let monitor = Point::from_monitor(&primary_monitor()?)?;
+    let targets = [
+        ("cs2", config.cs2(), config.geometry().cs2()),
+        ("5e", config.e5(), config.geometry().e5()),
+    ];
+
+    for (name, point, geometry) in targets {
+        // Absolute point is configured, geometry is not used
+        if point.is_some() {
+            continue;
+        }
+        geometry
+            .region(monitor)
+            .check_within(&monitor)
+            .map_err(|e| anyhow::anyhow!("[geometry.{name}] {e}, check anchor, offset and size"))?;
+    }
+    Ok(())
+}
 
-    Ok(Point::new(mid_x - w, mid_y - h, mid_x + w, mid_y + h))
+fn primary_monitor() -> anyhow::Result<Monitor> {
+    Monitor::all()?
+        .into_iter()
+        .find(|monitor| monitor.is_primary().unwrap_or_default())
+        .ok_or_else(|| anyhow::anyhow!("Not found primary monitor"))
+}
+
+fn capture_monitor(monitor: &Monitor, region: Point) -> anyhow::Result<RgbaImage> {
+    #[cfg(feature = "wayland")]
+    if let Some(screencast) = screencast::get() {
+        return screencast.capture(region);
+    }
+
+    let bounds = Point::from_monitor(monitor)?;
+    region.check_within(&bounds)?;
+    // Relative to the monitor
+    Ok(monitor.capture_region(
+        (region.x() - bounds.x()) as u32,
+        (region.y() - bounds.y()) as u32,
+        region.width() as u32,
+        region.height() as u32,
+    )?)
 }
 
 fn screen_cap(point: PointOption, is_5e: bool) -> anyhow::Result<(Point, ImageType)> {
     let start = Instant::now();
-    let monitors = Monitor::all().unwrap();
+    let monitor = primary_monitor()?;
 
-    for monitor in monitors {
-        if !monitor.is_primary().unwrap_or_default() {
-            continue;
-        }
-        let real_point = match point {
-            PointOption::Some(point) => point,
-            PointOption::Transform(func) => func(monitor.clone()),
-            PointOption::None => determine_point(monitor.clone(), is_5e)?,
-        };
+    let real_point = match &point {
+        PointOption::Some(point) => *point,
+        PointOption::Transform(func) => func(monitor.clone()),
+        PointOption::Area(area, region) => region.apply(*area),
+        PointOption::Window(window, region) => region.apply(window.client()),
+        PointOption::None => determine_point(monitor.clone(), is_5e)?,
+    };
 
-        let image = monitor.capture_region(
-            real_point.x() as u32,
-            real_point.y() as u32,
-            real_point.width() as u32,
-            real_point.height() as u32,
-        )?;
-        //log::debug!("{real_point:?}");
-
-        //return Ok(DynamicImage::from(image).into_rgb8());
-        log::trace!("elapsed: {:?}", start.elapsed());
-        if SAVE_IMAGE.load(std::sync::atomic::Ordering::Relaxed) {
-            image.save(format!("{}.png", timestamp_fmt("%Y-%m-%d_%H-%M-%S-%3f")))?;
-        }
-        return Ok((real_point, DynamicImage::from(image).into_rgb8()));
+    let image = match point {
+        PointOption::Window(window, _) => window.capture(real_point)?,
+        _ => capture_monitor(&monitor, real_point)?,
+    };
+    //log::debug!("{real_point:?}");
+
+    //return Ok(DynamicImage::from(image).into_rgb8());
+    log::trace!("elapsed: {:?}", start.elapsed());
+    if SAVE_IMAGE.load(std::sync::atomic::Ordering::Relaxed) {
+        image.save(format!("{}.png", timestamp_fmt("%Y-%m-%d_%H-%M-%S-%3f")))?;
     }
-    Err(anyhow::anyhow!("Not found primary monitor"))
+    Ok((real_point, DynamicImage::from(image).into_rgb8()))
 }
 
 #[must_use]
@@ -207,9 +268,9 @@ fn match_algorithm(
     options: MatchOptions,
 ) -> SearchResult {
     let x_start = options.limit_x() / 2;
-    let x_end = pic_x as usize - x_start;
+    let x_end = (pic_x as usize).saturating_sub(x_start);
     let y_start = options.limit_y() / 2;
-    let y_end = pic_y as usize - y_start;
+    let y_end = (pic_y as usize).saturating_sub(y_start);
 
     for x in x_start..x_end {
         for y in y_start..y_end {
@@ -245,33 +306,205 @@ pub(crate) fn check_image_match(
 ) -> anyhow::Result<SearchResult> {
     print_inline!("Capture screen             ");
     let (point, current_screen) = screen_cap(point, is_5e)?;
+    let (width, height) = current_screen.dimensions();
+    if (width as usize) < options.limit_x() || (height as usize) < options.limit_y() {
+        return Err(RegionError::TooSmall {
+            region: point,
+            limit_x: options.limit_x(),
+            limit_y: options.limit_y(),
+        }
+        .into());
+    }
+    Ok(search_image(point, &current_screen, template, options))
+}
+
+/// Look for the template in an image captured at `point`
+fn search_image(
+    point: Point,
+    image: &ImageType,
+    template: &Matcher,
+    options: MatchOptions,
+) -> SearchResult {
     print_inline!("Marking area into Vec<bool>");
-    let (buff, count) = process_area(&current_screen, template, options);
+    let (buff, count) = process_area(image, template, options);
     if count < options.limit_x() * options.limit_y() {
         //log::debug!("Early exit");
-        return Ok(SearchResult::NotFound);
+        return SearchResult::NotFound;
     }
     print_inline!("Checking point of interest");
     //let instant = Instant::now();
-    let ret = match_algorithm(point, &buff, current_screen.dimensions(), options);
+    let ret = match_algorithm(point, &buff, image.dimensions(), options);
     //log::debug!("elapsed: {:?}", instant.elapsed());
-    Ok(ret)
+    ret
+}
+
+/// Search a region sized capture from `tests/fixtures/templates`. They are drawn flat with a
+/// little noise, text is white blocks
+#[cfg(test)]
+fn search_fixture(name: &str, template: &Matcher, options: MatchOptions) -> SearchResult {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/templates");
+    let image = image::ImageReader::open(format!("{path}/{name}"))
+        .unwrap()
+        .decode()
+        .unwrap()
+        .into_rgb8();
+    search_image(Point::default(), &image, template, options)
+}
+
+fn display_mouse(config: &String) -> anyhow::Result<()> {
+    let config = Configure::load(config)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default();
+    get_pos(config.input())
+}
+
+/// Best effort desktop notification, also shown in the status line
+fn notify(message: &str) {
+    log::info!("{message}");
+    update_status!("{message}");
+    #[cfg(not(windows))]
+    match std::process::Command::new("notify-send")
+        .args(["CS2 Auto Accept", message])
+        .spawn()
+    {
+        // Reaped off the main loop, it would stay a zombie otherwise
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => log::warn!("Run notify-send error: {e:?}"),
+    }
+}
+
+/// Notify on entering any of the configured states
+fn spawn_notifier(config: &NotifyConfig) -> anyhow::Result<()> {
+    let states = config.states().to_vec();
+    let events = state::subscribe();
+    std::thread::Builder::new()
+        .name("notifier".into())
+        .spawn(move || {
+            for event in events {
+                if states.contains(&event.to()) {
+                    notify(&format!("{} -> {}", event.from(), event.to()));
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// No countdown for a pop the policy blocks anyway, `handle_target` reports the rule
+fn accept_delay(config: &Configure, policy: &AcceptPolicy) -> Duration {
+    match policy.blocked_by() {
+        Some(_) => Duration::ZERO,
+        None => config.accept_delay(),
+    }
 }
 
-fn display_mouse() -> anyhow::Result<()> {
-    get_pos()
+/// How the popup looks once `accept-delay` is over
+enum DelayOutcome {
+    /// Where the button is, or `NotFound` if there was none to begin with
+    Button(SearchResult),
+    /// Button went away during the countdown, accepted by hand
+    HandledByUser,
 }
 
-fn handle_target(result: SearchResult) -> anyhow::Result<bool> {
+/// Leave the popup to the user for `accept-delay`, the region is checked again every second
+fn wait_accept_delay(
+    result: SearchResult,
+    machine: &mut StateMachine,
+    delay: Duration,
+    point: &PointOption,
+    is_5e: bool,
+    template: &Matcher,
+    options: MatchOptions,
+) -> anyhow::Result<DelayOutcome> {
+    if delay.is_zero() || matches!(result, SearchResult::NotFound) {
+        return Ok(DelayOutcome::Button(result));
+    }
+    machine.observe(Observation::AcceptFound);
+    log::info!("Accept button found, click in {delay:?} unless it is handled by hand");
+    let deadline = Instant::now() + delay;
+    let mut result = result;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || EXIT_SIGNAL.get().is_some() {
+            break;
+        }
+        let seconds = remaining.as_secs_f32().ceil() as u64;
+        update_status!(@countdown Some(seconds));
+        print_inline!("Accept in {seconds}s                ");
+        sleep(remaining.min(Duration::from_secs(1)));
+
+        result = check_image_match(point.clone(), is_5e, template, options)?;
+        if let SearchResult::NotFound = result {
+            log::info!("Accept button disappeared during the delay, handled by the user");
+            update_status!(@countdown None);
+            update_status!("Match handled by the user, not clicking");
+            machine.observe(Observation::HandledByUser);
+            return Ok(DelayOutcome::HandledByUser);
+        }
+    }
+    update_status!(@countdown None);
+    Ok(DelayOutcome::Button(result))
+}
+
+/// `pids` and `title` identify the target window, used when it has to be activated first
+fn handle_target(
+    result: SearchResult,
+    machine: &mut StateMachine,
+    input: &mut dyn InputBackend,
+    policy: &mut AcceptPolicy,
+    config: &Configure,
+    pids: &[u32],
+    title: &str,
+) -> anyhow::Result<bool> {
     if let SearchResult::Found(pos1, pos2) = result {
+        machine.observe(Observation::AcceptFound);
         log::debug!("Mouse point: x: {pos1}, y: {pos2}");
         update_status!(pos1, pos2);
+        let dry_run = DRY_RUN.load(std::sync::atomic::Ordering::Relaxed);
+
+        if let Some(rule) = policy.blocked_by() {
+            log::info!("Accept blocked by policy rule {rule}");
+            print_inline!("Accept blocked by policy     ");
+            return Ok(false);
+        }
+
+        let guard = config.mouse_guard();
+        if guard.enabled() && !dry_run && !wait_mouse_idle(input, guard)? {
+            log::info!("Mouse is in use, defer click");
+            print_inline!("Mouse is in use, defer click     ");
+            if guard.notify() {
+                notify("Match found, click deferred while the mouse is in use");
+            }
+            return Ok(false);
+        }
+
+        let activate = config.activate_window();
+        let previous = if activate.enabled() && !dry_run {
+            match window::bring_to_front(pids, title, activate.timeout()) {
+                Ok(previous) => previous,
+                Err(e) => {
+                    log::warn!("Activate target window error, not clicking: {e:?}");
+                    return Ok(false);
+                }
+            }
+        } else {
+            None
+        };
+
         update_status!("Performance click");
-        move_mouse_click(
-            pos1 as i32,
-            pos2 as i32,
-            DRY_RUN.load(std::sync::atomic::Ordering::Relaxed),
-        )?;
+        move_mouse_click(input, pos1 as i32, pos2 as i32, dry_run, config.click())?;
+        machine.observe(Observation::Clicked);
+        if !dry_run {
+            policy.record_accept();
+        }
+
+        if let Some(previous) = previous.filter(|_| activate.restore_focus()) {
+            log::debug!("Restore focus to {previous}");
+            window::focus(previous.id(), activate.timeout())
+                .inspect_err(|e| log::warn!("Restore focus error: {e:?}"))
+                .ok();
+        }
 
         return Ok(true);
     }
@@ -279,32 +512,98 @@ fn handle_target(result: SearchResult) -> anyhow::Result<bool> {
     Ok(false)
 }
 
+/// Single click on a menu button, skipped while the user is moving the mouse
+fn click_button(
+    input: &mut dyn InputBackend,
+    config: &Configure,
+    (x, y): (usize, usize),
+    name: &str,
+) -> anyhow::Result<bool> {
+    let dry_run = DRY_RUN.load(std::sync::atomic::Ordering::Relaxed);
+    let guard = config.mouse_guard();
+    if guard.enabled() && !dry_run && !wait_mouse_idle(input, guard)? {
+        log::info!("Mouse is in use, not clicking {name}");
+        return Ok(false);
+    }
+    update_status!("Click {name}");
+    move_mouse_click(input, x as i32, y as i32, dry_run, config.click().once())?;
+    Ok(true)
+}
+
+/// Click GO in the lobby to search again after a failed match
+fn requeue(
+    processes: &ProcessWatcher,
+    area: &GameArea,
+    input: &mut dyn InputBackend,
+    config: &Configure,
+) -> anyhow::Result<()> {
+    let SearchResult::Found(x, y) = target_requeue::find_go_button(area, config.requeue())? else {
+        log::warn!("GO button not found, search again by hand");
+        return Ok(());
+    };
+    // GO also cancels a search, CS2 may have queued again by itself
+    if matches!(
+        target_main::check_primary_exec(processes, area)?,
+        CheckResult::NeedProcess
+    ) {
+        log::info!("Searching again already, GO not clicked");
+        return Ok(());
+    }
+    click_button(input, config, (x, y), "GO")?;
+    Ok(())
+}
+
+/// Also returns early (with `false`) once the console log has something to report
 fn sleep_until_exit(second: u64) -> bool {
-    for _ in 0..(second * 2) {
+    for _ in 0..(second * 10) {
         if EXIT_SIGNAL.get().is_some() {
             return true;
         }
-        sleep(Duration::from_millis(500));
+        if console_log::pending() {
+            break;
+        }
+        sleep(Duration::from_millis(100));
     }
     false
 }
 
-fn real_main(config: &String, force_distance: bool) -> anyhow::Result<()> {
-    let config = Configure::load(config)
-        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
-        .unwrap_or_default();
-    let mut sys = sysinfo::System::new_with_specifics(
-        RefreshKind::nothing().with_processes(
-            ProcessRefreshKind::everything()
-                .without_cpu()
-                .without_disk_usage()
-                .without_memory(),
-        ),
-    );
+/// What `real_main` knows about the game, kept when it is restarted after an error
+#[derive(Default)]
+struct Session {
+    machine: StateMachine,
+    fast_scan_until: Option<Instant>,
+    /// Last time the popup was up, a failed match is only told apart shortly after
+    last_popup: Option<Instant>,
+    /// Region error reported last, cleared once a round gets through
+    last_region_error: Option<RegionError>,
+}
+
+fn real_main(
+    config: &Configure,
+    force_distance: bool,
+    policy: &mut AcceptPolicy,
+    input: &mut dyn InputBackend,
+    session: &mut Session,
+) -> anyhow::Result<()> {
+    let Session {
+        machine,
+        fast_scan_until,
+        last_popup,
+        last_region_error,
+    } = session;
+    let mut processes = ProcessWatcher::new();
 
     let options = MatchOptions::new(force_distance, X_LIMIT, Y_LIMIT);
     let options_5e = MatchOptions::new(force_distance, X_LIMIT_5E, Y_LIMIT_5E);
-    let mut last_match;
+    let video = video_config::resolve(config.cs2_video());
+    let window_capture = config.window_capture();
+    let search_digits = config
+        .search_timer()
+        .enabled()
+        .then(|| search_timer::Digits::load(config.search_timer().digits()))
+        .transpose()
+        .inspect_err(|e| log::error!("Search timer disabled: {e:?}"))
+        .unwrap_or_default();
     #[cfg(feature = "obs")]
     let obs_tx = config
         .obs()
@@ -313,34 +612,92 @@ fn real_main(config: &String, force_distance: bool) -> anyhow::Result<()> {
     #[cfg(feature = "obs")]
     let mut official_ds = 0;
 
+    let mut checked = false;
     loop {
-        last_match = "N/A";
-        sys.refresh_all();
+        // Every region resolved in the last round, the error is reported again if it comes back
+        if std::mem::replace(&mut checked, true) {
+            last_region_error.take();
+        }
+        for event in processes.refresh(config.process()) {
+            log::info!("Process {event:?}");
+        }
+
+        match console_log::take() {
+            Some(console_log::ConsoleEvent::MatchFound) => {
+                log::info!("Console log: match found, scanning");
+                fast_scan_until.replace(Instant::now() + FAST_SCAN_DURATION);
+            }
+            Some(console_log::ConsoleEvent::Connecting) => {
+                fast_scan_until.take();
+                // The match started, a red notice from now on is not a failed accept
+                last_popup.take();
+                machine.observe(Observation::Cs2Playing);
+            }
+            None => {}
+        }
+        let fast_scan = fast_scan_until.is_some_and(|until| Instant::now() < until);
+        if matches!(machine.state(), State::MatchFound | State::Accepted) {
+            last_popup.replace(Instant::now());
+        }
 
-        match target_5e::check_need_handle(sys.processes()) {
+        let e5_result = target_5e::check_need_handle(&processes);
+        let e5_running = !matches!(e5_result, CheckResult::Next);
+        match e5_result {
             CheckResult::NeedProcess => {
                 print_inline!("Match 5e     ");
-                last_match = "5e";
+                machine.observe(Observation::E5Waiting);
 
-                if !target_5e::is_5e_foreground(config.e5_title()) {
+                if config.require_foreground().e5()
+                    && !target_5e::is_5e_foreground(&processes.e5_pids(), config.e5_title())
+                {
                     print_inline!("[5e] Not foreground     ");
                     sleep_until_exit!(config.interval().handle_success());
                     continue;
                 }
 
-                let ret = check_image_match(
-                    config.e5().into(),
+                let point = match config.e5() {
+                    Some(point) => PointOption::Some(point),
+                    None => GameArea::detect(
+                        window_capture,
+                        &processes.e5_pids(),
+                        Some(config.e5_title()),
+                        None,
+                    )
+                    .point(Region::Geometry(config.geometry().e5()))?,
+                };
+                let ret =
+                    check_image_match(point.clone(), true, &target_5e::MATCH_TEMPLATE, options_5e)?;
+                let ret = match wait_accept_delay(
+                    ret,
+                    machine,
+                    accept_delay(config, policy),
+                    &point,
                     true,
                     &target_5e::MATCH_TEMPLATE,
                     options_5e,
-                )?;
-                if handle_target(ret)? {
+                )? {
+                    DelayOutcome::Button(ret) => ret,
+                    DelayOutcome::HandledByUser => {
+                        sleep_until_exit!(config.interval().handle_success());
+                        continue;
+                    }
+                };
+                if handle_target(
+                    ret,
+                    machine,
+                    input,
+                    policy,
+                    config,
+                    &processes.e5_pids(),
+                    config.e5_title(),
+                )? {
                     sleep_until_exit!(config.interval().handle_success());
                     continue;
                 }
             }
             CheckResult::NoNeedProcess => {
                 print_inline!("[5e] User is playing     ");
+                machine.observe(Observation::E5Playing);
                 send_obs_command!(obs_tx);
                 sleep_until_exit!(config.interval().e5_wait());
                 continue;
@@ -348,37 +705,173 @@ fn real_main(config: &String, force_distance: bool) -> anyhow::Result<()> {
             CheckResult::Next => {}
         }
 
-        match target_main::check_primary_exec(sys.processes())? {
+        #[cfg(feature = "gsi")]
+        if config.gsi().enabled()
+            && gsi::current().is_some_and(|state| state.in_game())
+            && processes.cs2_running()
+        {
+            print_inline!("[cs] In game                     ");
+            last_popup.take();
+            machine.observe(Observation::Cs2Playing);
+            send_obs_command!(obs_tx, 15, official_ds);
+            sleep_until_exit!(config.interval().cs2_wait());
+            continue;
+        }
+
+        let cs2_area = GameArea::detect(
+            window_capture,
+            &processes.cs2_pids(),
+            Some(CS2_WINDOW_TITLE),
+            video,
+        );
+        let cs2_result = match target_main::check_primary_exec(&processes, &cs2_area)? {
+            // Lobby indicator does not matter once the console log saw the match
+            CheckResult::NoNeedProcess if fast_scan => CheckResult::NeedProcess,
+            result => result,
+        };
+        match cs2_result {
             CheckResult::NeedProcess => {
                 print_inline!("Match CS2     ");
-                last_match = "cs";
                 send_obs_command!(obs_tx, 5, official_ds);
 
+                if config.require_foreground().cs2()
+                    && !window::is_foreground(&processes.cs2_pids(), Some(CS2_WINDOW_TITLE))
+                {
+                    // The popup may only be covered, the machine keeps a popup state for now
+                    machine.observe(Observation::Cs2Searching);
+                    print_inline!("[cs] Not foreground     ");
+                    sleep_until_exit!(config.interval().handle_success());
+                    continue;
+                }
+
+                let point = match config.cs2() {
+                    Some(point) => PointOption::Some(point),
+                    None => cs2_area.point(Region::Geometry(config.geometry().cs2()))?,
+                };
                 //log::debug!("Check cs main");
-                let ret = check_image_match(
-                    config.cs2().into(),
+                let ret =
+                    check_image_match(point.clone(), false, &target_main::MATCH_TEMPLATE, options)?;
+                let ret = match wait_accept_delay(
+                    ret,
+                    machine,
+                    accept_delay(config, policy),
+                    &point,
                     false,
                     &target_main::MATCH_TEMPLATE,
                     options,
-                )?;
-                if handle_target(ret)? {
+                )? {
+                    DelayOutcome::Button(ret) => ret,
+                    DelayOutcome::HandledByUser => {
+                        sleep_until_exit!(config.interval().handle_success());
+                        continue;
+                    }
+                };
+                // The lobby indicator stays visible behind the popup, only a plain search
+                // has a timer to read
+                if matches!(ret, SearchResult::NotFound)
+                    && machine.observe(Observation::Cs2Searching) == State::Searching
+                    && let Some(digits) = &search_digits
+                    && queue_time::wants_search_timer()
+                {
+                    match search_timer::read_screen(digits, &cs2_area, config.search_timer()) {
+                        Ok(Some(elapsed)) => {
+                            log::info!("Search timer shows {}s", elapsed.as_secs());
+                            queue_time::set_search_elapsed(elapsed)
+                                .inspect_err(|e| log::warn!("Search timer error: {e:?}"))
+                                .ok();
+                        }
+                        Ok(None) => log::debug!("Search timer not recognised"),
+                        Err(e) => log::warn!("Read search timer error: {e:?}"),
+                    }
+                }
+                if handle_target(
+                    ret,
+                    machine,
+                    input,
+                    policy,
+                    config,
+                    &processes.cs2_pids(),
+                    CS2_WINDOW_TITLE,
+                )? {
                     sleep_until_exit!(config.interval().handle_success());
                     continue;
                 }
             }
             CheckResult::NoNeedProcess => {
+                if config.reconnect().enabled()
+                    && target_reconnect::should_check(machine)
+                    && let SearchResult::Found(x, y) =
+                        target_reconnect::check_reconnect(&cs2_area, config.reconnect())?
+                {
+                    log::info!("Reconnect button found, last session ended in game");
+                    if click_button(input, config, (x, y), "Reconnect")? {
+                        machine.observe(Observation::Reconnected);
+                    }
+                    sleep_until_exit!(config.interval().handle_success());
+                    continue;
+                }
+
+                // Lobby indicator is also gone while the accepted match is loading, so only
+                // a popup seen just before tells a failed match apart
+                if config.requeue().enabled()
+                    && last_popup.is_some_and(|at| at.elapsed() < target_requeue::FAILED_WINDOW)
+                    && target_requeue::check_failed_accept(&cs2_area, config.requeue())?
+                {
+                    log::info!("Match failed to start, someone did not accept");
+                    // The notice stays for a while, GO must not be clicked twice
+                    last_popup.take();
+                    machine.observe(Observation::AcceptFailed);
+                    if config.requeue().click_go() {
+                        requeue(&processes, &cs2_area, input, config)?;
+                    }
+                    sleep_until_exit!(config.interval().handle_success());
+                    continue;
+                }
+                #[cfg(feature = "gsi")]
+                let (menu, left) = match gsi::current().filter(|_| config.gsi().enabled()) {
+                    Some(state) => (!state.in_game(), state == gsi::GameState::Left),
+                    None => (false, false),
+                };
+                #[cfg(not(feature = "gsi"))]
+                let (menu, left) = (false, false);
                 print_inline!("[cs] Not searching              ");
+                machine.observe(match (menu, left) {
+                    (_, true) => Observation::MatchLeft,
+                    (true, false) => Observation::Cs2Menu,
+                    (false, false) => Observation::Cs2Idle,
+                });
                 send_obs_command!(obs_tx, 15, official_ds);
                 sleep_until_exit!(config.interval().cs2_wait());
                 continue;
             }
+            CheckResult::Next if !e5_running => {
+                // GSI keeps the last state for a while after CS2 is gone
+                #[cfg(feature = "gsi")]
+                let mid_match =
+                    config.gsi().enabled() && gsi::current().is_some_and(|state| state.mid_match());
+                #[cfg(not(feature = "gsi"))]
+                let mid_match = false;
+                machine.observe(match mid_match {
+                    true => Observation::GameLost,
+                    false => Observation::NoProcess,
+                });
+            }
             CheckResult::Next => {}
         }
 
-        print_inline!("[{last_match}] Sleep                      ");
+        print_inline!(
+            "[{}] Sleep {}                      ",
+            machine.state(),
+            queue_time::status()
+        );
         if DRY_RUN.load(std::sync::atomic::Ordering::Relaxed) {
             sleep_until_exit!(2);
-        } else if last_match.len() == 3 {
+        } else if fast_scan {
+            if EXIT_SIGNAL.get().is_some() {
+                break;
+            }
+            sleep(Duration::from_millis(500));
+        } else if machine.state() == State::Idle {
             sleep_until_exit!(config.interval().long());
         } else {
             sleep_until_exit!(config.interval().each());
@@ -388,15 +881,85 @@ fn real_main(config: &String, force_distance: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn real_main_guarder(config: &String, force_distance: bool) -> anyhow::Result<()> {
+fn real_main_guarder(config_file: &String, force_distance: bool) -> anyhow::Result<()> {
+    let config = Configure::load(config_file)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default();
+    validate_geometry(&config).inspect_err(|e| log::error!("{e}"))?;
+    // Lives across restarts of the main loop, counts belong to the whole session
+    let mut policy = AcceptPolicy::new(config.policy()).inspect_err(|e| log::error!("{e}"))?;
+    // A backend that can not start will not start on the next round either
+    let mut input = platform_impl::backend(config.input())
+        .inspect_err(|e| log::error!("Input backend error: {e:?}"))?;
+    if config.input().backend() == configure::InputKind::Uinput {
+        if config.click().restore_cursor() {
+            log::warn!("restore-cursor is not supported with the uinput backend, it is skipped");
+        }
+        if config.mouse_guard().enabled() {
+            log::warn!("[mouse-guard] is not supported with the uinput backend, it is skipped");
+        }
+    }
+    #[cfg(feature = "gsi")]
+    if config.gsi().enabled() {
+        gsi::spawn(config.gsi())
+            .inspect_err(|e| log::error!("GSI listener error: {e:?}"))
+            .ok();
+    }
+    #[cfg(feature = "gsi")]
+    let gsi_enabled = config.gsi().enabled();
+    #[cfg(not(feature = "gsi"))]
+    let gsi_enabled = false;
+    if config.reconnect().enabled() && !gsi_enabled {
+        log::warn!("Reconnect needs [gsi] to tell a crash from the end of a match, it never fires");
+    }
+    if config.queue_time().enabled() {
+        queue_time::spawn(config.queue_time(), config_file)
+            .inspect_err(|e| log::error!("Queue time error: {e:?}"))
+            .ok();
+    }
+    if !config.notify().states().is_empty() {
+        spawn_notifier(config.notify())
+            .inspect_err(|e| log::error!("Notifier error: {e:?}"))
+            .ok();
+    }
+    if config.console_log().enabled() {
+        console_log::spawn(config.console_log())
+            .inspect_err(|e| log::error!("Console log watcher error: {e:?}"))
+            .ok();
+    }
+    #[cfg(feature = "wayland")]
+    if config.screencast().enabled() {
+        screencast::init(config.screencast())
+            .inspect_err(|e| log::error!("Screencast error, fallback to xcap: {e:?}"))
+            .ok();
+    }
+
     log::info!("Started checking");
     let mut err = None;
+    // Like the policy, the session survives a restart of the main loop
+    let mut session = Session::default();
     while EXIT_SIGNAL.get().is_none() {
-        if let Err(e) = real_main(config, force_distance)
-            .inspect_err(|e| log::error!("Main thread error: {e:?}"))
-        {
-            err.replace(e);
+        let Err(e) = real_main(
+            &config,
+            force_distance,
+            &mut policy,
+            input.as_mut(),
+            &mut session,
+        ) else {
+            continue;
+        };
+        // Region will not fix itself in a moment, report it once and wait
+        if let Some(region_error) = e.downcast_ref::<RegionError>().copied() {
+            if session.last_region_error.replace(region_error) != Some(region_error) {
+                log::error!("Invalid region: {region_error}");
+            }
+            if sleep_until_exit(config.interval().long()) {
+                break;
+            }
+            continue;
         }
+        log::error!("Main thread error: {e:?}");
+        err.replace(e);
     }
     if let Some(e) = err { Err(e) } else { Ok(()) }
 }
@@ -425,9 +988,11 @@ fn main() -> anyhow::Result<()> {
             arg!(-n --"dry-run" "Dry run (do not click)"),
             arg!(--"save-image" "Save image each time take"),
             arg!(--"force-distance" "Use distance algorithm to check image"),
+            arg!(--"cs2-video-cfg" <path> "Read game resolution from this cs2_video.txt"),
         ])
         .subcommands([
             Command::new("mouse").about("Display current mouse position"),
+            Command::new("foreground").about("Display the focused window and its pid"),
             Command::new("get-color")
                 .about("Get RGB list from image file")
                 .args(&[
@@ -448,8 +1013,12 @@ fn main() -> anyhow::Result<()> {
                 .args(&[arg!(<FILE> "Test image"), arg!(--"5e" "Enable 5e match")]),
             Command::new("match")
                 .about("Help subcommand for debug matcher")
-                .args(&[arg!(<function> "Functions to match")
-                    .value_parser([PossibleValue::new("cs2-lobby")])])
+                .args(&[arg!(<function> "Functions to match").value_parser([
+                    PossibleValue::new("cs2-lobby"),
+                    PossibleValue::new("cs2-failed"),
+                    PossibleValue::new("cs2-go"),
+                    PossibleValue::new("cs2-reconnect"),
+                ])])
                 .subcommands(&[
                     Command::new("screen").about("From screen").args(&[
                         arg!([interval] "Fetch interval(ms)")
@@ -469,6 +1038,42 @@ fn main() -> anyhow::Result<()> {
                         .hide(cfg!(not(feature = "jpeg"))),
                 ])
                 .subcommand_required(true),
+            Command::new("process-overhead")
+                .about("Compare process polling with the old refresh_all")
+                .args(&[arg!([rounds] "Refresh rounds")
+                    .default_value("50")
+                    .value_parser(clap::value_parser!(u32))]),
+            Command::new("search-timer")
+                .about("Make and check the digit bitmaps of the lobby search timer")
+                .subcommands(&[
+                    Command::new("capture")
+                        .about("Save the timer strip from the screen while searching")
+                        .args(&[arg!(--output <FILE> "Image file").default_value("strip.png")]),
+                    Command::new("extract")
+                        .about("Cut a strip of a known time into one bitmap per glyph")
+                        .args(&[
+                            arg!(<FILE> "Strip image"),
+                            arg!(<TEXT> "Time shown in the strip, e.g. 1:23"),
+                            arg!(--output <DIR> "Bitmap directory").default_value("digits"),
+                        ]),
+                    Command::new("read")
+                        .about("Read saved strips with the configured bitmaps")
+                        .args(&[arg!(<PATH> "Strip image or directory of them")]),
+                ])
+                .subcommand_required(true),
+            Command::new("gsi")
+                .about("Manage the CS2 Game State Integration config")
+                .args(&[
+                    arg!(--"cs2-dir" <path> "CS2 install directory, found from Steam by default"),
+                ])
+                .subcommands(&[
+                    Command::new("install").about(
+                        "Write the GSI config into CS2 and save its token to configure file",
+                    ),
+                    Command::new("uninstall").about("Remove the GSI config from CS2"),
+                ])
+                .subcommand_required(true)
+                .hide(cfg!(not(feature = "gsi"))),
         ])
         .get_matches();
 
@@ -482,8 +1087,12 @@ fn main() -> anyhow::Result<()> {
         std::sync::atomic::Ordering::Relaxed,
     );
     let force_distance = matches.get_flag("force-distance");
+    if let Some(path) = matches.get_one::<String>("cs2-video-cfg") {
+        CS2_VIDEO_CFG.set(path.clone()).unwrap();
+    }
     match matches.subcommand() {
-        Some(("mouse", _)) => display_mouse(),
+        Some(("mouse", _)) => display_mouse(matches.get_one("CONFIG").unwrap()),
+        Some(("foreground", _)) => window::display_foreground(),
         Some(("get-color", matches)) => load_and_display(
             &matches.get_many::<String>("FILE").unwrap(),
             matches.get_one("output"),
@@ -499,10 +1108,12 @@ fn main() -> anyhow::Result<()> {
             matches.get_flag("5e"),
             force_distance,
         ),
-        Some(("match", matches)) => {
-            let function = matches.get_one::<String>("function").unwrap();
-            match matches.subcommand() {
+        Some(("match", sub_matches)) => {
+            let config = matches.get_one::<String>("CONFIG").unwrap();
+            let function = sub_matches.get_one::<String>("function").unwrap();
+            match sub_matches.subcommand() {
                 Some(("screen", matches)) => continue_test_area(
+                    config,
                     function,
                     force_distance,
                     matches.get_flag("save"),
@@ -517,6 +1128,142 @@ fn main() -> anyhow::Result<()> {
                 _ => unreachable!(),
             }
         }
+        Some(("process-overhead", sub_matches)) => measure_process_overhead(
+            matches.get_one("CONFIG").unwrap(),
+            *sub_matches.get_one("rounds").unwrap(),
+        ),
+        Some(("search-timer", sub_matches)) => {
+            let config = matches.get_one::<String>("CONFIG").unwrap();
+            match sub_matches.subcommand() {
+                Some(("capture", matches)) => {
+                    search_timer::capture(config, matches.get_one::<String>("output").unwrap())
+                }
+                Some(("extract", matches)) => search_timer::extract(
+                    matches.get_one::<String>("FILE").unwrap(),
+                    matches.get_one::<String>("TEXT").unwrap(),
+                    matches.get_one::<String>("output").unwrap(),
+                ),
+                Some(("read", matches)) => {
+                    search_timer::read_files(config, matches.get_one::<String>("PATH").unwrap())
+                }
+                _ => unreachable!(),
+            }
+        }
+        Some(("gsi", sub_matches)) => {
+            let cs2_dir = sub_matches.get_one::<String>("cs2-dir");
+            match sub_matches.subcommand() {
+                Some(("install", _)) => gsi::install(matches.get_one("CONFIG").unwrap(), cs2_dir),
+                Some(("uninstall", _)) => gsi::uninstall(cs2_dir),
+                _ => unreachable!(),
+            }
+        }
         _ => gui::gui_entry(matches.get_one("CONFIG").unwrap(), force_distance),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        platform_impl::{
+            ButtonAction,
+            mock::{Action, RecordingBackend},
+        },
+        types::MouseButton,
+    };
+
+    fn run(config: &str, input: &mut RecordingBackend) -> (bool, State) {
+        let config: Configure = toml::from_str(config).unwrap();
+        let mut policy = AcceptPolicy::new(config.policy()).unwrap();
+        let mut machine = StateMachine::default();
+        let clicked = handle_target(
+            SearchResult::Found(100, 200),
+            &mut machine,
+            input,
+            &mut policy,
+            &config,
+            &[],
+            CS2_WINDOW_TITLE,
+        )
+        .unwrap();
+        (clicked, machine.state())
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn frame_clicks_count_and_hold() {
+        let mut input = RecordingBackend::new((5, 5));
+        let config = "[click]\ncount = 2\nhold-ms = 40\nmove-delay-ms = 50\ninterval-ms = 300\nbutton = \"right\"";
+        assert_eq!(run(config, &mut input), (true, State::Accepted));
+
+        let press = Action::Button(MouseButton::Right, ButtonAction::Press);
+        let release = Action::Button(MouseButton::Right, ButtonAction::Release);
+        assert_eq!(
+            input.timeline(),
+            [
+                (ms(0), Action::Move(100, 200)),
+                (ms(0), Action::Wait(ms(50))),
+                (ms(50), press),
+                (ms(50), Action::Wait(ms(40))),
+                (ms(90), release),
+                (ms(90), Action::Wait(ms(300))),
+                (ms(390), press),
+                (ms(390), Action::Wait(ms(40))),
+                (ms(430), release),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_blocked_by_policy() {
+        let config: Configure =
+            toml::from_str("[policy]\nmax-accepts = 1\n[click]\ncount = 1\nmove-delay-ms = 0")
+                .unwrap();
+        let mut policy = AcceptPolicy::new(config.policy()).unwrap();
+        let mut machine = StateMachine::default();
+        let mut input = RecordingBackend::new((5, 5));
+        let mut frame = |input: &mut RecordingBackend, machine: &mut StateMachine| {
+            handle_target(
+                SearchResult::Found(100, 200),
+                machine,
+                input,
+                &mut policy,
+                &config,
+                &[],
+                CS2_WINDOW_TITLE,
+            )
+            .unwrap()
+        };
+
+        assert!(frame(&mut input, &mut machine));
+        let clicked = input.timeline().to_vec();
+        assert_eq!(
+            clicked,
+            [
+                (ms(0), Action::Move(100, 200)),
+                (ms(0), Action::Wait(ms(0))),
+                (
+                    ms(0),
+                    Action::Button(MouseButton::Left, ButtonAction::Click)
+                ),
+            ]
+        );
+
+        // Next match pops, the limit is reached and nothing is sent
+        machine.observe(Observation::NoProcess);
+        assert!(!frame(&mut input, &mut machine));
+        assert_eq!(input.timeline(), clicked);
+        assert_eq!(machine.state(), State::MatchFound);
+    }
+
+    #[test]
+    fn frame_deferred_by_guard() {
+        let mut input = RecordingBackend::drifting((5, 5), (4, 0));
+        let config = "[mouse-guard]\nenabled = true\nthreshold = 2\ndefer-ms = 0";
+        assert_eq!(run(config, &mut input), (false, State::MatchFound));
+        assert_eq!(input.timeline(), [(ms(0), Action::Wait(ms(25)))]);
+    }
+}
@@ -73,10 +73,16 @@ pub(crate) mod dir_match {
 
         let template = match function {
             "cs2-lobby" => &crate::target_main::LOBBY_MATCH_TEMPLATE,
+            "cs2-failed" => &crate::target_requeue::FAILED_TEMPLATE,
+            "cs2-go" => &crate::target_requeue::GO_TEMPLATE,
+            "cs2-reconnect" => &crate::target_reconnect::RECONNECT_TEMPLATE,
             _ => unreachable!(),
         };
         let opts = match function {
             "cs2-lobby" => MatchOptions::new(false, 2, 2),
+            "cs2-failed" => crate::target_requeue::FAILED_OPTIONS,
+            "cs2-go" => crate::target_requeue::GO_OPTIONS,
+            "cs2-reconnect" => crate::target_reconnect::RECONNECT_OPTIONS,
             _ => unreachable!(),
         };
 
@@ -29,3 +29,14 @@ pub(crate) mod gui {
         crate::real_main_guarder(config, force_distance)
     }
 }
+
+#[cfg(not(feature = "gsi"))]
+pub(crate) mod gsi {
+    pub(crate) fn install(_: &String, _: Option<&String>) -> ! {
+        unimplemented!("To use this function, enable \"gsi\" feature")
+    }
+
+    pub(crate) fn uninstall(_: Option<&String>) -> ! {
+        unimplemented!("To use this function, enable \"gsi\" feature")
+    }
+}
@@ -1,11 +1,14 @@
-use std::sync::mpsc;
+use std::{sync::mpsc, time::Duration};
 
 use anyhow::{Context, bail};
 use base64::{Engine, engine::general_purpose::STANDARD as B64};
 use sha2::{Digest, Sha256};
 use tungstenite::connect;
 
-use crate::configure::ObsIntegration;
+use crate::{
+    configure::ObsIntegration,
+    state::{self, State},
+};
 
 /// obs-websocket v5 opcodes
 const OP_HELLO: u64 = 0;
@@ -14,6 +17,9 @@ const OP_IDENTIFIED: u64 = 2;
 const OP_REQUEST: u64 = 6;
 const OP_REQUEST_RESPONSE: u64 = 7;
 
+/// How often state changes are picked up between commands
+const STATE_POLL: Duration = Duration::from_millis(500);
+
 /// Commands the main thread can send to the OBS thread.
 pub enum ObsCmd {
     /// Check if OBS is recording and start it if not.
@@ -195,40 +201,56 @@ fn ensure_recording(ws: &mut Ws) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn obs_thread(config: ObsIntegration, rx: mpsc::Receiver<ObsCmd>) {
+/// Connect if needed, then make sure the scene is set and OBS is recording
+fn handle_ensure(config: &ObsIntegration, ws: &mut Option<Ws>) {
+    // Reconnect if the connection was dropped or not yet established
+    if ws.is_none() {
+        match handshake(config) {
+            Ok(conn) => {
+                //log::info!("OBS WebSocket connected");
+                *ws = Some(conn);
+            }
+            Err(e) => {
+                log::warn!("OBS connect failed: {e:#}");
+                return;
+            }
+        }
+    }
+
+    if let Some(conn) = ws.as_mut() {
+        if let Some(scene) = config.scene()
+            && let Err(e) = ensure_scene(conn, scene)
+        {
+            log::warn!("OBS ensure_scene failed: {e:#}");
+            *ws = None;
+            return;
+        }
+        if let Err(e) = ensure_recording(conn) {
+            log::warn!("OBS ensure_recording failed: {e:#}");
+            *ws = None; // drop broken connection, reconnect next time
+        }
+    }
+}
+
+/// Recording should already run once a match is accepted or loaded, `events` starts it then
+fn obs_thread(
+    config: ObsIntegration,
+    rx: mpsc::Receiver<ObsCmd>,
+    events: mpsc::Receiver<state::StateEvent>,
+) {
     let mut ws: Option<Ws> = None;
 
-    for cmd in rx {
-        match cmd {
-            ObsCmd::EnsureRecording => {
-                // Reconnect if the connection was dropped or not yet established
-                if ws.is_none() {
-                    match handshake(&config) {
-                        Ok(conn) => {
-                            //log::info!("OBS WebSocket connected");
-                            ws = Some(conn);
-                        }
-                        Err(e) => {
-                            log::warn!("OBS connect failed: {e:#}");
-                            continue;
-                        }
-                    }
-                }
-
-                if let Some(conn) = ws.as_mut() {
-                    if let Some(scene) = config.scene() {
-                        if let Err(e) = ensure_scene(conn, scene) {
-                            log::warn!("OBS ensure_scene failed: {e:#}");
-                            ws = None;
-                            continue;
-                        }
-                    }
-                    if let Err(e) = ensure_recording(conn) {
-                        log::warn!("OBS ensure_recording failed: {e:#}");
-                        ws = None; // drop broken connection, reconnect next time
-                    }
-                }
-            }
+    loop {
+        match rx.recv_timeout(STATE_POLL) {
+            Ok(ObsCmd::EnsureRecording) => handle_ensure(&config, &mut ws),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if events
+            .try_iter()
+            .any(|event| matches!(event.to(), State::Accepted | State::InGame))
+        {
+            handle_ensure(&config, &mut ws);
         }
     }
 
@@ -239,13 +261,14 @@ fn obs_thread(config: ObsIntegration, rx: mpsc::Receiver<ObsCmd>) {
 }
 
 /// Spawn the OBS worker thread. Returns a sender to control it.
-/// The thread exits automatically when the sender is dropped.
+/// The thread also follows state changes and exits automatically when the sender is dropped.
 pub fn spawn(config: ObsIntegration) -> mpsc::Sender<ObsCmd> {
     //log::info!("Starting obs thread");
     let (tx, rx) = mpsc::channel();
+    let events = state::subscribe();
     std::thread::Builder::new()
         .name("obs-worker".into())
-        .spawn(move || obs_thread(config, rx))
+        .spawn(move || obs_thread(config, rx, events))
         .expect("failed to spawn obs thread");
     tx
 }
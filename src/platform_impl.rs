@@ -1,9 +1,310 @@
+use std::{
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    configure::{ClickConfig, InputConfig, InputKind, MouseGuard},
+    types::MouseButton,
+};
+
 #[cfg(not(windows))]
 mod fallback;
+// Stands in for a real backend in tests
+#[cfg(test)]
+pub(crate) mod mock;
+#[cfg(all(feature = "uinput", target_os = "linux"))]
+mod uinput;
 #[cfg(windows)]
 mod windows;
+#[cfg(not(windows))]
+mod xtest;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ButtonAction {
+    Press,
+    Release,
+    Click,
+}
+
+/// Synthetic mouse input, the click sequence only talks to this
+pub(crate) trait InputBackend {
+    /// Absolute screen coordinates
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()>;
+
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()>;
+
+    fn position(&mut self) -> anyhow::Result<(i32, i32)>;
+
+    fn wait(&mut self, duration: Duration) {
+        sleep(duration);
+    }
+
+    /// Clock that `wait` advances, the mouse guard measures its windows with it
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn perform(&mut self, step: ClickStep) -> anyhow::Result<()> {
+        match step {
+            ClickStep::Move(x, y) => self.move_to(x, y),
+            ClickStep::Wait(duration) => {
+                self.wait(duration);
+                Ok(())
+            }
+            ClickStep::Press(button) => self.button(button, ButtonAction::Press),
+            ClickStep::Release(button) => self.button(button, ButtonAction::Release),
+            ClickStep::Click(button) => self.button(button, ButtonAction::Click),
+        }
+    }
+}
 
 #[cfg(not(windows))]
-pub(crate) use fallback::*;
+pub(crate) fn backend(config: &InputConfig) -> anyhow::Result<Box<dyn InputBackend>> {
+    Ok(match config.backend() {
+        InputKind::Auto | InputKind::Enigo => Box::new(fallback::EnigoBackend::new()?),
+        InputKind::Xtest => Box::new(xtest::XTestBackend::new(config.display())?),
+        InputKind::Uinput => uinput_backend()?,
+    })
+}
+
+#[cfg(all(feature = "uinput", target_os = "linux"))]
+fn uinput_backend() -> anyhow::Result<Box<dyn InputBackend>> {
+    Ok(Box::new(uinput::UinputBackend::new()?))
+}
+
+#[cfg(all(not(windows), not(all(feature = "uinput", target_os = "linux"))))]
+fn uinput_backend() -> anyhow::Result<Box<dyn InputBackend>> {
+    Err(anyhow::anyhow!(
+        "To use uinput input backend, enable \"uinput\" feature (Linux only)"
+    ))
+}
+
 #[cfg(windows)]
-pub(crate) use windows::*;
+pub(crate) fn backend(config: &InputConfig) -> anyhow::Result<Box<dyn InputBackend>> {
+    if config.backend() != InputKind::Auto {
+        log::warn!(
+            "Input backend {:?} is not available on Windows, use SendInput",
+            config.backend()
+        );
+    }
+    Ok(Box::new(windows::WinsafeBackend))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ClickStep {
+    Move(i32, i32),
+    Wait(Duration),
+    Press(MouseButton),
+    Release(MouseButton),
+    Click(MouseButton),
+}
+
+/// Steps of one accept click, every backend runs them in order
+#[derive(Clone, Debug)]
+pub(crate) struct ClickSequence {
+    steps: Vec<ClickStep>,
+}
+
+impl ClickSequence {
+    pub(crate) fn new(x: i32, y: i32, config: &ClickConfig) -> Self {
+        let [offset_x, offset_y] = config.offset();
+        let button = config.button();
+        let mut steps = vec![
+            ClickStep::Move(x + offset_x, y + offset_y),
+            ClickStep::Wait(config.move_delay()),
+        ];
+
+        for n in 0..config.count() {
+            if n > 0 {
+                steps.push(ClickStep::Wait(config.interval()));
+            }
+            match config.hold() {
+                Some(hold) => steps.extend([
+                    ClickStep::Press(button),
+                    ClickStep::Wait(hold),
+                    ClickStep::Release(button),
+                ]),
+                None => steps.push(ClickStep::Click(button)),
+            }
+        }
+        Self { steps }
+    }
+
+    /// Only the cursor movement, nothing is clicked
+    pub(crate) fn dry_run(&self) -> impl Iterator<Item = ClickStep> {
+        self.steps
+            .iter()
+            .copied()
+            .filter(|step| matches!(step, ClickStep::Move(..)))
+    }
+
+    pub(crate) fn steps(&self) -> &[ClickStep] {
+        &self.steps
+    }
+}
+
+pub(crate) fn move_mouse_click(
+    input: &mut dyn InputBackend,
+    x: i32,
+    y: i32,
+    is_test: bool,
+    config: ClickConfig,
+) -> anyhow::Result<()> {
+    // Not every backend can read the cursor back
+    let original = input
+        .position()
+        .inspect_err(|e| log::debug!("Get cursor position error: {e:?}"))
+        .ok();
+    let sequence = ClickSequence::new(x, y, &config);
+
+    if is_test {
+        match original {
+            Some((original_x, original_y)) => {
+                log::info!("Dry run: cursor at ({original_x}, {original_y}), click at ({x}, {y})")
+            }
+            None => log::info!("Dry run: cursor at unknown position, click at ({x}, {y})"),
+        }
+        for step in sequence.dry_run() {
+            input.perform(step)?;
+        }
+    } else {
+        for step in sequence.steps() {
+            input.perform(*step)?;
+        }
+    }
+
+    if config.restore_cursor() {
+        match original {
+            Some((original_x, original_y)) => input.move_to(original_x, original_y)?,
+            None => log::warn!("Cursor position is unknown, can not restore it"),
+        }
+    }
+    Ok(())
+}
+
+const CURSOR_SAMPLE_INTERVAL: Duration = Duration::from_millis(25);
+
+/// Whether the cursor moves more than `threshold` pixels within `window`
+fn cursor_moving(
+    input: &mut dyn InputBackend,
+    window: Duration,
+    threshold: i32,
+) -> anyhow::Result<bool> {
+    let Ok((start_x, start_y)) = input.position() else {
+        // Backend can not read the cursor, nothing to guard
+        return Ok(false);
+    };
+    let start = input.now();
+    while input.now().duration_since(start) < window {
+        input.wait(CURSOR_SAMPLE_INTERVAL);
+        let (x, y) = input.position()?;
+        if (x - start_x).abs() > threshold || (y - start_y).abs() > threshold {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Wait up to `defer` for the user to stop moving the mouse, `false` if they kept moving
+pub(crate) fn wait_mouse_idle(
+    input: &mut dyn InputBackend,
+    guard: MouseGuard,
+) -> anyhow::Result<bool> {
+    let start = input.now();
+    while cursor_moving(input, guard.sample(), guard.threshold())? {
+        if input.now().duration_since(start) >= guard.defer() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+pub(crate) fn get_pos(config: &InputConfig) -> anyhow::Result<()> {
+    let mut input = backend(config)?;
+    let mut prev_x = 0;
+    let mut prev_y = 0;
+
+    loop {
+        let (x, y) = input.position()?;
+        if prev_x != x || prev_y != y {
+            println!("x: {x}, y: {y}");
+            prev_x = x;
+            prev_y = y;
+        }
+        sleep(Duration::from_millis(100));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        mock::{Action, RecordingBackend},
+        *,
+    };
+
+    fn click_config(text: &str) -> ClickConfig {
+        toml::from_str(text).unwrap()
+    }
+
+    fn guard(text: &str) -> MouseGuard {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn click_holds_and_restores() {
+        let config = click_config(
+            "count = 2\nhold-ms = 30\nbutton = \"right\"\noffset = [3, -2]\nrestore-cursor = true",
+        );
+        let mut input = RecordingBackend::new((5, 5));
+        move_mouse_click(&mut input, 100, 200, false, config).unwrap();
+
+        let press = Action::Button(MouseButton::Right, ButtonAction::Press);
+        let release = Action::Button(MouseButton::Right, ButtonAction::Release);
+        let hold = Action::Wait(Duration::from_millis(30));
+        assert_eq!(
+            input.actions(),
+            [
+                Action::Move(103, 198),
+                Action::Wait(config.move_delay()),
+                press,
+                hold,
+                release,
+                Action::Wait(config.interval()),
+                press,
+                hold,
+                release,
+                Action::Move(5, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn dry_run_only_moves() {
+        let mut input = RecordingBackend::new((5, 5));
+        move_mouse_click(&mut input, 100, 200, true, click_config("count = 3")).unwrap();
+        assert_eq!(input.actions(), [Action::Move(100, 200)]);
+    }
+
+    #[test]
+    fn still_cursor_is_idle() {
+        let mut input = RecordingBackend::new((5, 5));
+        assert!(wait_mouse_idle(&mut input, guard("sample-ms = 50")).unwrap());
+        assert_eq!(input.actions(), [Action::Wait(CURSOR_SAMPLE_INTERVAL); 2]);
+    }
+
+    #[test]
+    fn moving_cursor_is_not_idle() {
+        let mut input = RecordingBackend::drifting((5, 5), (4, 0));
+        assert!(!wait_mouse_idle(&mut input, guard("threshold = 2\ndefer-ms = 0")).unwrap());
+        assert_eq!(input.actions(), [Action::Wait(CURSOR_SAMPLE_INTERVAL)]);
+    }
+
+    #[test]
+    fn moving_cursor_defers() {
+        // Every sample sees the movement, one sample per look until defer-ms is over
+        let mut input = RecordingBackend::drifting((5, 5), (4, 0));
+        assert!(!wait_mouse_idle(&mut input, guard("threshold = 2\ndefer-ms = 100")).unwrap());
+        assert_eq!(input.actions(), [Action::Wait(CURSOR_SAMPLE_INTERVAL); 4]);
+    }
+}
@@ -1,32 +1,42 @@
-use std::{thread::sleep, time::Duration};
-
 use enigo::{Enigo, Mouse};
 
-pub(crate) fn move_mouse_click(x: i32, y: i32, is_test: bool) -> anyhow::Result<()> {
-    let mut eg = Enigo::new(&Default::default())?;
-    eg.move_mouse(x, y, enigo::Coordinate::Abs)?;
+use super::{ButtonAction, InputBackend};
+use crate::types::MouseButton;
+
+pub(crate) struct EnigoBackend {
+    eg: Enigo,
+}
 
-    if !is_test {
-        sleep(Duration::from_millis(50));
-        eg.button(enigo::Button::Left, enigo::Direction::Click)?;
-        sleep(Duration::from_secs(1));
-        eg.button(enigo::Button::Left, enigo::Direction::Click)?;
+impl EnigoBackend {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            eg: Enigo::new(&Default::default())?,
+        })
     }
-    Ok(())
 }
 
-pub(crate) fn get_pos() -> anyhow::Result<()> {
-    let eg = enigo::Enigo::new(&enigo::Settings::default())?;
-    let mut prev_x = 0;
-    let mut prev_y = 0;
+impl InputBackend for EnigoBackend {
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()> {
+        self.eg.move_mouse(x, y, enigo::Coordinate::Abs)?;
+        Ok(())
+    }
+
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()> {
+        let button = match button {
+            MouseButton::Left => enigo::Button::Left,
+            MouseButton::Right => enigo::Button::Right,
+            MouseButton::Middle => enigo::Button::Middle,
+        };
+        let direction = match action {
+            ButtonAction::Press => enigo::Direction::Press,
+            ButtonAction::Release => enigo::Direction::Release,
+            ButtonAction::Click => enigo::Direction::Click,
+        };
+        self.eg.button(button, direction)?;
+        Ok(())
+    }
 
-    loop {
-        let (x, y) = eg.location()?;
-        if prev_x != x || prev_y != y {
-            println!("x: {x}, y: {y}");
-            prev_x = x;
-            prev_y = y;
-        }
-        sleep(Duration::from_millis(100));
+    fn position(&mut self) -> anyhow::Result<(i32, i32)> {
+        Ok(self.eg.location()?)
     }
 }
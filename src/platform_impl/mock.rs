@@ -0,0 +1,79 @@
+use std::time::{Duration, Instant};
+
+use super::{ButtonAction, InputBackend};
+use crate::types::MouseButton;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Move(i32, i32),
+    Button(MouseButton, ButtonAction),
+    Wait(Duration),
+}
+
+/// Records every action instead of sending it, waits only advance a virtual clock
+#[derive(Debug)]
+pub(crate) struct RecordingBackend {
+    position: (i32, i32),
+    /// Added to the cursor on every read, a user moving the mouse
+    drift: (i32, i32),
+    /// Each action with the virtual time it started at
+    actions: Vec<(Duration, Action)>,
+    start: Instant,
+    elapsed: Duration,
+}
+
+impl RecordingBackend {
+    pub(crate) fn new(position: (i32, i32)) -> Self {
+        Self::drifting(position, (0, 0))
+    }
+
+    pub(crate) fn drifting(position: (i32, i32), drift: (i32, i32)) -> Self {
+        Self {
+            position,
+            drift,
+            actions: vec![],
+            start: Instant::now(),
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn actions(&self) -> Vec<Action> {
+        self.actions.iter().map(|(_, action)| *action).collect()
+    }
+
+    pub(crate) fn timeline(&self) -> &[(Duration, Action)] {
+        &self.actions
+    }
+
+    fn record(&mut self, action: Action) {
+        self.actions.push((self.elapsed, action));
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()> {
+        self.position = (x, y);
+        self.record(Action::Move(x, y));
+        Ok(())
+    }
+
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()> {
+        self.record(Action::Button(button, action));
+        Ok(())
+    }
+
+    fn position(&mut self) -> anyhow::Result<(i32, i32)> {
+        let position = self.position;
+        self.position = (position.0 + self.drift.0, position.1 + self.drift.1);
+        Ok(position)
+    }
+
+    fn wait(&mut self, duration: Duration) {
+        self.record(Action::Wait(duration));
+        self.elapsed += duration;
+    }
+
+    fn now(&self) -> Instant {
+        self.start + self.elapsed
+    }
+}
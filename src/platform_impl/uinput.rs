@@ -0,0 +1,193 @@
+//! Virtual absolute pointer through `/dev/uinput`.
+//!
+//! Works under Wayland compositors that ignore synthetic X11/libei input, the kernel device
+//! looks like any other tablet-style pointer to them.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    thread::sleep,
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+use xcap::Monitor;
+
+use super::{ButtonAction, InputBackend};
+use crate::types::{MouseButton, Point};
+
+const UINPUT_PATH: &str = "/dev/uinput";
+const DEVICE_NAME: &[u8] = b"cs2-auto-accept virtual pointer";
+
+// linux/uinput.h, _IO/_IOW with type 'U'
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+const UI_DEV_SETUP: libc::c_ulong = 0x405c_5503;
+const UI_ABS_SETUP: libc::c_ulong = 0x401c_5504;
+const UI_SET_EVBIT: libc::c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x4004_5565;
+const UI_SET_ABSBIT: libc::c_ulong = 0x4004_5567;
+
+// linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// Compositors need a moment to pick up a new device
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+pub(crate) struct UinputBackend {
+    device: File,
+    // Union of all monitors, the device axes span exactly this area
+    area: Point,
+}
+
+fn ioctl(device: &File, request: libc::c_ulong, arg: libc::c_ulong) -> anyhow::Result<()> {
+    if unsafe { libc::ioctl(device.as_raw_fd(), request as _, arg) } < 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("ioctl {request:#x} on {UINPUT_PATH}"));
+    }
+    Ok(())
+}
+
+fn open_device() -> anyhow::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(UINPUT_PATH)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow!(
+                "{UINPUT_PATH} does not exist, load the kernel module with `modprobe uinput`"
+            ),
+            std::io::ErrorKind::PermissionDenied => anyhow!(
+                "{UINPUT_PATH} is not writable by this user, add a udev rule like \
+                 `KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\"` and join the input group"
+            ),
+            _ => anyhow!(e).context(format!("Open {UINPUT_PATH}")),
+        })
+}
+
+fn desktop_area() -> anyhow::Result<Point> {
+    let monitors = Monitor::all()?
+        .iter()
+        .map(Point::from_monitor)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let left = monitors.iter().map(Point::x).min();
+    let top = monitors.iter().map(Point::y).min();
+    let right = monitors.iter().map(|m| m.x() + m.width()).max();
+    let bottom = monitors.iter().map(|m| m.y() + m.height()).max();
+    match (left, top, right, bottom) {
+        (Some(left), Some(top), Some(right), Some(bottom)) => {
+            Ok(Point::new(left, top, right, bottom))
+        }
+        _ => Err(anyhow!("No monitor found")),
+    }
+}
+
+impl UinputBackend {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        let area = desktop_area()?;
+        let device = open_device()?;
+
+        for event in [EV_SYN, EV_KEY, EV_ABS] {
+            ioctl(&device, UI_SET_EVBIT, event as _)?;
+        }
+        for key in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
+            ioctl(&device, UI_SET_KEYBIT, key as _)?;
+        }
+        for (code, size) in [(ABS_X, area.width()), (ABS_Y, area.height())] {
+            ioctl(&device, UI_SET_ABSBIT, code as _)?;
+            let setup = libc::uinput_abs_setup {
+                code,
+                absinfo: libc::input_absinfo {
+                    value: 0,
+                    minimum: 0,
+                    maximum: size - 1,
+                    fuzz: 0,
+                    flat: 0,
+                    resolution: 0,
+                },
+            };
+            ioctl(&device, UI_ABS_SETUP, &setup as *const _ as _)?;
+        }
+
+        let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        for (dst, src) in setup.name.iter_mut().zip(DEVICE_NAME) {
+            *dst = *src as _;
+        }
+        ioctl(&device, UI_DEV_SETUP, &setup as *const _ as _)?;
+        ioctl(&device, UI_DEV_CREATE, 0)?;
+        log::info!("Created uinput pointer for desktop {area}");
+        sleep(SETTLE_TIME);
+
+        Ok(Self { device, area })
+    }
+
+    fn emit(&mut self, events: &[(u16, u16, i32)]) -> anyhow::Result<()> {
+        let mut buffer = Vec::with_capacity((events.len() + 1) * size_of::<libc::input_event>());
+        for &(r#type, code, value) in events.iter().chain([&(EV_SYN, SYN_REPORT, 0)]) {
+            let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+            event.type_ = r#type;
+            event.code = code;
+            event.value = value;
+            buffer.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(
+                    &event as *const _ as *const u8,
+                    size_of::<libc::input_event>(),
+                )
+            });
+        }
+        self.device
+            .write_all(&buffer)
+            .context("Write uinput events")?;
+        Ok(())
+    }
+}
+
+impl InputBackend for UinputBackend {
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()> {
+        Point::new(x, y, x + 1, y + 1).check_within(&self.area)?;
+        self.emit(&[
+            (EV_ABS, ABS_X, x - self.area.x()),
+            (EV_ABS, ABS_Y, y - self.area.y()),
+        ])
+    }
+
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()> {
+        let code = match button {
+            MouseButton::Left => BTN_LEFT,
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+        };
+        match action {
+            ButtonAction::Press => self.emit(&[(EV_KEY, code, 1)]),
+            ButtonAction::Release => self.emit(&[(EV_KEY, code, 0)]),
+            ButtonAction::Click => {
+                self.emit(&[(EV_KEY, code, 1)])?;
+                self.emit(&[(EV_KEY, code, 0)])
+            }
+        }
+    }
+
+    /// uinput is write only, the compositor may have moved the cursor since our last move
+    fn position(&mut self) -> anyhow::Result<(i32, i32)> {
+        Err(anyhow!("uinput can not read the cursor position"))
+    }
+}
+
+impl Drop for UinputBackend {
+    fn drop(&mut self) {
+        ioctl(&self.device, UI_DEV_DESTROY, 0)
+            .inspect_err(|e| log::warn!("Destroy uinput device error: {e:?}"))
+            .ok();
+    }
+}
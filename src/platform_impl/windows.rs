@@ -1,41 +1,45 @@
-use std::{thread::sleep, time::Duration};
-
 use winsafe::{GetCursorPos, HwKbMouse, MOUSEINPUT, SendInput, SetCursorPos, co::MOUSEEVENTF};
 
-pub(crate) fn move_mouse_click(x: i32, y: i32, is_test: bool) -> anyhow::Result<()> {
-    SetCursorPos(x, y)?;
+use super::{ButtonAction, InputBackend};
+use crate::types::MouseButton;
 
-    let press_event = HwKbMouse::Mouse(MOUSEINPUT {
-        dwFlags: MOUSEEVENTF::LEFTDOWN,
-        time: 10,
-        ..Default::default()
-    });
+pub(crate) struct WinsafeBackend;
+
+fn button_flags(button: MouseButton) -> (MOUSEEVENTF, MOUSEEVENTF) {
+    match button {
+        MouseButton::Left => (MOUSEEVENTF::LEFTDOWN, MOUSEEVENTF::LEFTUP),
+        MouseButton::Right => (MOUSEEVENTF::RIGHTDOWN, MOUSEEVENTF::RIGHTUP),
+        MouseButton::Middle => (MOUSEEVENTF::MIDDLEDOWN, MOUSEEVENTF::MIDDLEUP),
+    }
+}
 
-    let release_event = HwKbMouse::Mouse(MOUSEINPUT {
-        dwFlags: MOUSEEVENTF::LEFTUP,
+/// Press events keep the timestamp the click always sent, releases let the system stamp them
+fn mouse_event(flags: MOUSEEVENTF, time: u32) -> HwKbMouse {
+    HwKbMouse::Mouse(MOUSEINPUT {
+        dwFlags: flags,
+        time,
         ..Default::default()
-    });
+    })
+}
 
-    if !is_test {
-        sleep(Duration::from_millis(50));
-        SendInput(&[press_event, release_event])?;
-        sleep(Duration::from_secs(1));
-        SendInput(&[press_event, release_event])?;
+impl InputBackend for WinsafeBackend {
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()> {
+        SetCursorPos(x, y)?;
+        Ok(())
     }
 
-    Ok(())
-}
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()> {
+        let (down, up) = button_flags(button);
+        match action {
+            ButtonAction::Press => SendInput(&[mouse_event(down, 10)])?,
+            ButtonAction::Release => SendInput(&[mouse_event(up, 0)])?,
+            ButtonAction::Click => SendInput(&[mouse_event(down, 10), mouse_event(up, 0)])?,
+        };
+        Ok(())
+    }
 
-pub(crate) fn get_pos() -> anyhow::Result<()> {
-    let mut prev_x = 0;
-    let mut prev_y = 0;
-    loop {
+    fn position(&mut self) -> anyhow::Result<(i32, i32)> {
         let point = GetCursorPos()?;
-        if prev_x != point.x || prev_y != point.y {
-            println!("x: {}, y: {}", point.x, point.y);
-            prev_x = point.x;
-            prev_y = point.y;
-        }
-        sleep(Duration::from_millis(100));
+        Ok((point.x, point.y))
     }
 }
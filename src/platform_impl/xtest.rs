@@ -0,0 +1,84 @@
+use anyhow::Context;
+use x11rb::{
+    connection::{Connection, RequestConnection},
+    protocol::{
+        xproto::{
+            BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, ConnectionExt as _, MOTION_NOTIFY_EVENT,
+            Window,
+        },
+        xtest::{self, ConnectionExt as _},
+    },
+    rust_connection::RustConnection,
+};
+
+use super::{ButtonAction, InputBackend};
+use crate::types::MouseButton;
+
+/// Fake input through the XTEST extension, one connection for the whole run
+pub(crate) struct XTestBackend {
+    conn: RustConnection,
+    root: Window,
+}
+
+impl XTestBackend {
+    /// `display` like `:0`, `DISPLAY` is used if not set
+    pub(crate) fn new(display: Option<&str>) -> anyhow::Result<Self> {
+        let (conn, screen) = x11rb::connect(display).with_context(|| match display {
+            Some(display) => format!("Connect X11 display {display:?}"),
+            None => "Connect X11 display from DISPLAY, set input.display if it is not set".into(),
+        })?;
+        conn.extension_information(xtest::X11_EXTENSION_NAME)?
+            .context("XTEST extension is not available on this X server")?;
+        let root = conn.setup().roots[screen].root;
+        Ok(Self { conn, root })
+    }
+
+    fn fake(&self, r#type: u8, detail: u8, x: i16, y: i16) -> anyhow::Result<()> {
+        self.conn
+            .xtest_fake_input(r#type, detail, x11rb::CURRENT_TIME, self.root, x, y, 0)?
+            .check()?;
+        Ok(())
+    }
+}
+
+impl InputBackend for XTestBackend {
+    fn move_to(&mut self, x: i32, y: i32) -> anyhow::Result<()> {
+        // Detail 0 means absolute motion
+        self.fake(MOTION_NOTIFY_EVENT, 0, x as i16, y as i16)
+    }
+
+    fn button(&mut self, button: MouseButton, action: ButtonAction) -> anyhow::Result<()> {
+        let detail = match button {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+        };
+        if matches!(action, ButtonAction::Press | ButtonAction::Click) {
+            self.fake(BUTTON_PRESS_EVENT, detail, 0, 0)?;
+        }
+        if matches!(action, ButtonAction::Release | ButtonAction::Click) {
+            self.fake(BUTTON_RELEASE_EVENT, detail, 0, 0)?;
+        }
+        Ok(())
+    }
+
+    fn position(&mut self) -> anyhow::Result<(i32, i32)> {
+        let reply = self.conn.query_pointer(self.root)?.reply()?;
+        Ok((reply.root_x as i32, reply.root_y as i32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs an X server with XTEST, run under xvfb-run with --ignored"]
+    fn move_reads_back() {
+        let mut backend = XTestBackend::new(None).unwrap();
+        for (x, y) in [(10, 20), (123, 45)] {
+            backend.move_to(x, y).unwrap();
+            assert_eq!(backend.position().unwrap(), (x, y));
+        }
+    }
+}
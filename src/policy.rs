@@ -0,0 +1,323 @@
+//! Rules deciding whether a found accept button may be clicked.
+//!
+//! Evaluated in the `[policy]` time zone, the system one if not set.
+
+use std::time::{Duration, Instant};
+
+use jiff::{
+    Timestamp,
+    civil::{DateTime, Time, Weekday},
+    tz::TimeZone,
+};
+use serde::Deserialize;
+
+use crate::configure::PolicyConfig;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+fn parse_weekday(day: &str) -> anyhow::Result<Weekday> {
+    let lower = day.to_lowercase();
+    Ok(match lower.get(..3).unwrap_or_default() {
+        "mon" => Weekday::Monday,
+        "tue" => Weekday::Tuesday,
+        "wed" => Weekday::Wednesday,
+        "thu" => Weekday::Thursday,
+        "fri" => Weekday::Friday,
+        "sat" => Weekday::Saturday,
+        "sun" => Weekday::Sunday,
+        _ => return Err(anyhow::anyhow!("Unknown weekday {day:?}")),
+    })
+}
+
+#[derive(Deserialize)]
+struct RawSchedule {
+    #[serde(default)]
+    days: Vec<String>,
+    start: String,
+    end: String,
+}
+
+/// Accepting is allowed from `start` to `end` on `days` (every day if empty), e.g.
+/// `{ days = ["fri", "sat"], start = "20:00", end = "02:00" }`.
+/// An `end` before `start` runs past midnight into the next day.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RawSchedule")]
+pub struct Schedule {
+    days: Vec<Weekday>,
+    start: Time,
+    end: Time,
+}
+
+impl TryFrom<RawSchedule> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawSchedule) -> Result<Self, Self::Error> {
+        Ok(Self {
+            days: raw
+                .days
+                .iter()
+                .map(|day| parse_weekday(day))
+                .collect::<anyhow::Result<_>>()?,
+            start: raw.start.parse()?,
+            end: raw.end.parse()?,
+        })
+    }
+}
+
+impl Schedule {
+    fn on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, now: DateTime) -> bool {
+        let (day, time) = (now.weekday(), now.time());
+        if self.start <= self.end {
+            self.on(day) && self.start <= time && time < self.end
+        } else {
+            (self.on(day) && time >= self.start) || (self.on(day.previous()) && time < self.end)
+        }
+    }
+}
+
+/// RFC 3339 timestamp, or a local date and time in the policy time zone
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub enum PauseUntil {
+    Timestamp(Timestamp),
+    Local(DateTime),
+}
+
+impl TryFrom<String> for PauseUntil {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if let Ok(timestamp) = s.parse() {
+            return Ok(Self::Timestamp(timestamp));
+        }
+        Ok(Self::Local(s.parse().map_err(|e| {
+            anyhow::anyhow!("Invalid pause-until {s:?}: {e}")
+        })?))
+    }
+}
+
+impl PauseUntil {
+    fn resolve(&self, tz: &TimeZone) -> anyhow::Result<Timestamp> {
+        match self {
+            Self::Timestamp(timestamp) => Ok(*timestamp),
+            Self::Local(datetime) => Ok(datetime.to_zoned(tz.clone())?.timestamp()),
+        }
+    }
+}
+
+/// The rule that blocked an accept
+#[derive(Clone, Debug)]
+pub(crate) enum Rule {
+    PauseUntil(String),
+    Schedule,
+    MaxAccepts(u32),
+    Cooldown { after: u32, left: Duration },
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PauseUntil(until) => write!(f, "pause-until, paused until {until}"),
+            Self::Schedule => write!(f, "schedule, outside active hours"),
+            Self::MaxAccepts(max) => write!(f, "max-accepts, {max} accepts this session"),
+            Self::Cooldown { after, left } => write!(
+                f,
+                "cooldown after {after} accepts, {}s left",
+                left.as_secs()
+            ),
+        }
+    }
+}
+
+/// Policy state of this session, one session is one run of the program
+pub(crate) struct AcceptPolicy {
+    config: PolicyConfig,
+    tz: TimeZone,
+    pause_until: Option<Timestamp>,
+    accepts: u32,
+    last_accept: Option<Instant>,
+}
+
+impl AcceptPolicy {
+    pub(crate) fn new(config: &PolicyConfig) -> anyhow::Result<Self> {
+        let tz = match config.timezone() {
+            Some(name) => TimeZone::get(name)
+                .map_err(|e| anyhow::anyhow!("[policy] timezone {name:?}: {e}"))?,
+            None => TimeZone::system(),
+        };
+        let pause_until = config
+            .pause_until()
+            .map(|pause| pause.resolve(&tz))
+            .transpose()?;
+        Ok(Self {
+            config: config.clone(),
+            tz,
+            pause_until,
+            accepts: 0,
+            last_accept: None,
+        })
+    }
+
+    /// First rule that forbids accepting right now
+    pub(crate) fn blocked_by(&self) -> Option<Rule> {
+        let now = Timestamp::now();
+        if let Some(until) = self.pause_until.filter(|until| now < *until) {
+            return Some(Rule::PauseUntil(
+                until
+                    .to_zoned(self.tz.clone())
+                    .strftime(TIME_FORMAT)
+                    .to_string(),
+            ));
+        }
+
+        let schedule = self.config.schedule();
+        let local = now.to_zoned(self.tz.clone()).datetime();
+        if !schedule.is_empty() && !schedule.iter().any(|s| s.contains(local)) {
+            return Some(Rule::Schedule);
+        }
+
+        self.counted_rule(self.last_accept.map(|last| last.elapsed()))
+    }
+
+    /// Rules counting accepts of this session, `since_last` is the time since the last one
+    fn counted_rule(&self, since_last: Option<Duration>) -> Option<Rule> {
+        if let Some(max) = self.config.max_accepts().filter(|max| self.accepts >= *max) {
+            return Some(Rule::MaxAccepts(max));
+        }
+
+        if let (Some(cooldown), Some(since_last)) = (self.config.cooldown(), since_last)
+            && cooldown.after() > 0
+            && self.accepts.is_multiple_of(cooldown.after())
+        {
+            let left = cooldown.duration().saturating_sub(since_last);
+            if !left.is_zero() {
+                return Some(Rule::Cooldown {
+                    after: cooldown.after(),
+                    left,
+                });
+            }
+        }
+        None
+    }
+
+    pub(crate) fn record_accept(&mut self) {
+        self.accepts += 1;
+        self.last_accept.replace(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(days: &[&str], start: &str, end: &str) -> Schedule {
+        Schedule::try_from(RawSchedule {
+            days: days.iter().map(|day| day.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn at(datetime: &str) -> DateTime {
+        datetime.parse().unwrap()
+    }
+
+    fn with_config(config: &str) -> AcceptPolicy {
+        AcceptPolicy::new(&toml::from_str(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn same_day_window() {
+        // 2026-10-19 is a Monday
+        let evening = schedule(&["mon"], "18:00", "23:30");
+        assert!(evening.contains(at("2026-10-19T18:00")));
+        assert!(evening.contains(at("2026-10-19T23:29:59")));
+        assert!(!evening.contains(at("2026-10-19T23:30")));
+        assert!(!evening.contains(at("2026-10-19T17:59")));
+        assert!(!evening.contains(at("2026-10-20T19:00")));
+    }
+
+    #[test]
+    fn window_past_midnight() {
+        let night = schedule(&[], "22:00", "02:00");
+        assert!(night.contains(at("2026-10-19T23:00")));
+        assert!(night.contains(at("2026-10-20T01:59")));
+        assert!(!night.contains(at("2026-10-20T02:00")));
+        assert!(!night.contains(at("2026-10-20T12:00")));
+    }
+
+    #[test]
+    fn weekday_rollover() {
+        // Saturday night runs into Sunday morning, Sunday night is not active
+        let weekend = schedule(&["sat"], "20:00", "02:00");
+        assert!(weekend.contains(at("2026-10-24T21:00")));
+        assert!(weekend.contains(at("2026-10-25T01:00")));
+        assert!(!weekend.contains(at("2026-10-25T21:00")));
+        assert!(!weekend.contains(at("2026-10-24T01:00")));
+
+        // Sunday rolls over into Monday
+        let sunday = schedule(&["Sunday"], "23:00", "01:00");
+        assert!(sunday.contains(at("2026-10-26T00:30")));
+        assert!(!sunday.contains(at("2026-10-27T00:30")));
+    }
+
+    #[test]
+    fn unknown_weekday() {
+        assert!(parse_weekday("someday").is_err());
+        assert_eq!(parse_weekday("TUE").unwrap(), Weekday::Tuesday);
+    }
+
+    #[test]
+    fn max_accepts() {
+        let mut policy = with_config("max-accepts = 2");
+        assert!(policy.counted_rule(None).is_none());
+        policy.record_accept();
+        assert!(policy.counted_rule(Some(Duration::ZERO)).is_none());
+        policy.record_accept();
+        assert!(matches!(
+            policy.counted_rule(Some(Duration::ZERO)),
+            Some(Rule::MaxAccepts(2))
+        ));
+    }
+
+    #[test]
+    fn cooldown_after_every_n_accepts() {
+        let mut policy = with_config("cooldown = { after = 2, minutes = 10 }");
+        let minute = Duration::from_secs(60);
+        policy.record_accept();
+        assert!(policy.counted_rule(Some(Duration::ZERO)).is_none());
+
+        policy.record_accept();
+        match policy.counted_rule(Some(3 * minute)) {
+            Some(Rule::Cooldown { after: 2, left }) => assert_eq!(left, 7 * minute),
+            rule => panic!("unexpected {rule:?}"),
+        }
+        assert!(policy.counted_rule(Some(10 * minute)).is_none());
+
+        // Third accept starts a new round, the fourth one cools down again
+        policy.record_accept();
+        assert!(policy.counted_rule(Some(Duration::ZERO)).is_none());
+        policy.record_accept();
+        assert!(policy.counted_rule(Some(minute)).is_some());
+    }
+
+    #[test]
+    fn cooldown_after_zero_never_blocks() {
+        let mut policy = with_config("cooldown = { after = 0, minutes = 10 }");
+        policy.record_accept();
+        assert!(policy.counted_rule(Some(Duration::ZERO)).is_none());
+    }
+
+    #[test]
+    fn pause_until() {
+        let policy = with_config(r#"pause-until = "2001-01-01T00:00:00Z""#);
+        assert!(policy.blocked_by().is_none());
+        let policy = with_config(r#"pause-until = "2999-01-01T00:00:00Z""#);
+        assert!(matches!(policy.blocked_by(), Some(Rule::PauseUntil(_))));
+    }
+}
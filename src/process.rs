@@ -0,0 +1,406 @@
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde::Deserialize;
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+use crate::configure::ProcessConfig;
+
+/// A missing target is looked for this long after it went away, doubling up to
+/// `FULL_SCAN_MAX_INTERVAL` while it stays missing
+const FULL_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+const FULL_SCAN_MAX_INTERVAL: Duration = Duration::from_secs(16);
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    #[default]
+    Exact,
+    /// `*` matches any characters, `?` matches one
+    Glob,
+    Regex,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MatchField {
+    Name,
+    Exe,
+    /// First argument only, the program as started
+    Argv0,
+    Cmdline,
+}
+
+#[derive(Deserialize)]
+struct RawMatcher {
+    name: Option<String>,
+    exe: Option<String>,
+    argv0: Option<String>,
+    cmdline: Option<String>,
+    #[serde(default)]
+    kind: MatchKind,
+}
+
+/// One of `name`, `exe`, `argv0` or `cmdline` compared against `kind` pattern, e.g.
+/// `{ argv0 = "*5EClient.exe", kind = "glob" }`. `cmdline` also matches a shell, an editor or
+/// grep that only has the pattern among its arguments
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "RawMatcher")]
+pub struct ProcessMatcher {
+    field: MatchField,
+    pattern: Regex,
+}
+
+impl TryFrom<RawMatcher> for ProcessMatcher {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawMatcher) -> Result<Self, Self::Error> {
+        let (field, pattern) = match (raw.name, raw.exe, raw.argv0, raw.cmdline) {
+            (Some(pattern), None, None, None) => (MatchField::Name, pattern),
+            (None, Some(pattern), None, None) => (MatchField::Exe, pattern),
+            (None, None, Some(pattern), None) => (MatchField::Argv0, pattern),
+            (None, None, None, Some(pattern)) => (MatchField::Cmdline, pattern),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Process matcher needs exactly one of name, exe, argv0 or cmdline"
+                ));
+            }
+        };
+        Self::new(field, raw.kind, &pattern)
+    }
+}
+
+impl ProcessMatcher {
+    fn new(field: MatchField, kind: MatchKind, pattern: &str) -> anyhow::Result<Self> {
+        let pattern = match kind {
+            MatchKind::Exact => format!("^{}$", regex::escape(pattern)),
+            MatchKind::Glob => format!(
+                "^{}$",
+                regex::escape(pattern)
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".")
+            ),
+            MatchKind::Regex => pattern.to_string(),
+        };
+        Ok(Self {
+            field,
+            pattern: Regex::new(&pattern)?,
+        })
+    }
+
+    pub fn name(name: &str) -> Self {
+        Self::new(MatchField::Name, MatchKind::Exact, name).unwrap()
+    }
+
+    pub fn argv0_glob(pattern: &str) -> Self {
+        Self::new(MatchField::Argv0, MatchKind::Glob, pattern).unwrap()
+    }
+
+    pub fn is_match(&self, process: &Process) -> bool {
+        match self.field {
+            MatchField::Name => self.pattern.is_match(&process.name().to_string_lossy()),
+            MatchField::Exe => process
+                .exe()
+                .is_some_and(|exe| self.pattern.is_match(&exe.to_string_lossy())),
+            MatchField::Argv0 => process
+                .cmd()
+                .first()
+                .is_some_and(|arg| self.pattern.is_match(&arg.to_string_lossy())),
+            MatchField::Cmdline => {
+                let cmdline = process
+                    .cmd()
+                    .iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.pattern.is_match(&cmdline)
+            }
+        }
+    }
+}
+
+fn find_pids<'a>(
+    processes: impl Iterator<Item = &'a Process>,
+    matchers: &[ProcessMatcher],
+) -> Vec<Pid> {
+    processes
+        .filter(|p| matchers.iter().any(|m| m.is_match(p)))
+        .map(Process::pid)
+        .collect()
+}
+
+/// PIDs of one target and when to look for it again while it is missing
+#[derive(Default)]
+struct Tracked {
+    pids: Vec<Pid>,
+    next_scan: Option<Instant>,
+    backoff: Duration,
+}
+
+impl Tracked {
+    fn scan_due(&self, now: Instant) -> bool {
+        self.pids.is_empty() && self.next_scan.is_none_or(|at| now >= at)
+    }
+
+    /// Back off while the target stays missing, start over once it was seen
+    fn scanned(&mut self, pids: Vec<Pid>, now: Instant) {
+        self.backoff = match pids.is_empty() {
+            true => (self.backoff * 2).clamp(FULL_SCAN_INTERVAL, FULL_SCAN_MAX_INTERVAL),
+            false => Duration::ZERO,
+        };
+        self.next_scan.replace(now + self.backoff);
+        self.pids = pids;
+    }
+
+    /// Gone since the last refresh, the target may be restarting right away
+    fn lost(&mut self, now: Instant) {
+        self.pids.clear();
+        self.backoff = Duration::ZERO;
+        self.next_scan.replace(now);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    Cs2,
+    E5,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ProcessEvent {
+    Started(Target, u32),
+    Exited(Target, u32),
+}
+
+/// Keeps track of CS2 and 5E processes.
+///
+/// Found PIDs are re-checked on their own each time. The whole process list is only scanned
+/// for a missing target, with a growing interval while it stays missing, so a target that
+/// never runs costs a scan every `FULL_SCAN_MAX_INTERVAL`.
+/// `System::refresh_all` also reads memory and CPU tables and every field of every process,
+/// run `process-overhead` subcommand to compare both on a machine.
+pub(crate) struct ProcessWatcher {
+    sys: System,
+    cs2: Tracked,
+    e5: Tracked,
+}
+
+impl ProcessWatcher {
+    pub(crate) fn new() -> Self {
+        Self {
+            sys: System::new(),
+            cs2: Tracked::default(),
+            e5: Tracked::default(),
+        }
+    }
+
+    // Name is always read, exe and cmdline are read once per process and only if needed
+    fn refresh_kind(config: &ProcessConfig) -> ProcessRefreshKind {
+        let matchers = || config.cs2().iter().chain(config.e5());
+        let mut kind = ProcessRefreshKind::nothing();
+        if matchers().any(|m| m.field == MatchField::Exe) {
+            kind = kind.with_exe(UpdateKind::OnlyIfNotSet);
+        }
+        if matchers().any(|m| matches!(m.field, MatchField::Argv0 | MatchField::Cmdline)) {
+            kind = kind.with_cmd(UpdateKind::OnlyIfNotSet);
+        }
+        kind
+    }
+
+    pub(crate) fn refresh(&mut self, config: &ProcessConfig) -> Vec<ProcessEvent> {
+        let kind = Self::refresh_kind(config);
+        let now = Instant::now();
+        let old = (self.cs2.pids.clone(), self.e5.pids.clone());
+
+        let cached = [self.cs2.pids.as_slice(), self.e5.pids.as_slice()].concat();
+        if !cached.is_empty() {
+            self.sys
+                .refresh_processes_specifics(ProcessesToUpdate::Some(&cached), true, kind);
+        }
+        for (tracked, matchers) in [(&mut self.cs2, config.cs2()), (&mut self.e5, config.e5())] {
+            if tracked.pids.is_empty() {
+                continue;
+            }
+            let sys = &self.sys;
+            let pids = find_pids(
+                tracked.pids.iter().filter_map(|pid| sys.process(*pid)),
+                matchers,
+            );
+            match pids.is_empty() {
+                true => tracked.lost(now),
+                false => tracked.pids = pids,
+            }
+        }
+
+        if self.cs2.scan_due(now) || self.e5.scan_due(now) {
+            self.sys
+                .refresh_processes_specifics(ProcessesToUpdate::All, true, kind);
+            for (tracked, matchers) in [(&mut self.cs2, config.cs2()), (&mut self.e5, config.e5())]
+            {
+                if tracked.scan_due(now) {
+                    tracked.scanned(find_pids(self.sys.processes().values(), matchers), now);
+                }
+            }
+        }
+
+        let mut events = vec![];
+        for (target, old, new) in [
+            (Target::Cs2, &old.0, &self.cs2.pids),
+            (Target::E5, &old.1, &self.e5.pids),
+        ] {
+            events.extend(
+                new.iter()
+                    .filter(|pid| !old.contains(pid))
+                    .map(|pid| ProcessEvent::Started(target, pid.as_u32())),
+            );
+            events.extend(
+                old.iter()
+                    .filter(|pid| !new.contains(pid))
+                    .map(|pid| ProcessEvent::Exited(target, pid.as_u32())),
+            );
+        }
+        events
+    }
+
+    /// Scan the whole list on next refresh even if every target is found
+    pub(crate) fn force_full_scan(&mut self) {
+        self.cs2 = Tracked::default();
+        self.e5 = Tracked::default();
+    }
+
+    pub(crate) fn cs2_running(&self) -> bool {
+        !self.cs2.pids.is_empty()
+    }
+
+    pub(crate) fn e5_running(&self) -> bool {
+        !self.e5.pids.is_empty()
+    }
+
+    pub(crate) fn cs2_pids(&self) -> Vec<u32> {
+        self.cs2.pids.iter().map(|pid| pid.as_u32()).collect()
+    }
+
+    pub(crate) fn e5_pids(&self) -> Vec<u32> {
+        self.e5.pids.iter().map(|pid| pid.as_u32()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(toml: &str) -> ProcessMatcher {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn exact_is_literal() {
+        let m = matcher(r#"name = "cs2.exe""#);
+        assert!(m.pattern.is_match("cs2.exe"));
+        assert!(!m.pattern.is_match("cs2xexe"));
+        assert!(!m.pattern.is_match("cs2.exe.old"));
+        assert!(!m.pattern.is_match("cs2"));
+    }
+
+    #[test]
+    fn glob() {
+        let m = matcher(
+            r#"argv0 = "*5EClient.exe"
+            kind = "glob""#,
+        );
+        assert_eq!(m.field, MatchField::Argv0);
+        assert!(m.pattern.is_match(r"C:\Program Files\5E\5EClient.exe"));
+        assert!(m.pattern.is_match("5EClient.exe"));
+        assert!(!m.pattern.is_match("5EClientXexe"));
+        assert!(!m.pattern.is_match(r"C:\5E\5EClient.exe --update"));
+
+        let m = matcher(
+            r#"name = "cs?"
+            kind = "glob""#,
+        );
+        assert!(m.pattern.is_match("cs2"));
+        assert!(!m.pattern.is_match("cs22"));
+        assert!(!m.pattern.is_match("cs"));
+    }
+
+    #[test]
+    fn regex() {
+        let m = matcher(
+            r#"exe = '[\\/]cs2(\.exe)?$'
+            kind = "regex""#,
+        );
+        assert_eq!(m.field, MatchField::Exe);
+        assert!(m.pattern.is_match("/opt/cs2/game/bin/linuxsteamrt64/cs2"));
+        assert!(m.pattern.is_match(r"D:\cs2\bin\win64\cs2.exe"));
+        assert!(!m.pattern.is_match(r"D:\cs2\bin\win64\cs2.exe.old"));
+    }
+
+    #[test]
+    fn invalid_matchers() {
+        for toml in [
+            "",
+            r#"kind = "glob""#,
+            "name = \"cs2\"\nexe = \"cs2\"",
+            "argv0 = \"cs2\"\ncmdline = \"cs2\"",
+            "name = \"(\"\nkind = \"regex\"",
+            "name = \"cs2\"\nkind = \"prefix\"",
+        ] {
+            assert!(toml::from_str::<ProcessMatcher>(toml).is_err(), "{toml:?}");
+        }
+    }
+
+    /// Fields are read from a real process, this test binary
+    #[test]
+    fn own_process() {
+        let pid = sysinfo::get_current_pid().unwrap();
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::Always)
+                .with_cmd(UpdateKind::Always),
+        );
+        let process = sys.process(pid).unwrap();
+        let argv0 = std::env::args().next().unwrap();
+        let exe_name = std::env::current_exe()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        assert!(ProcessMatcher::name(&process.name().to_string_lossy()).is_match(process));
+        assert!(!ProcessMatcher::name("cs2").is_match(process));
+        assert!(ProcessMatcher::argv0_glob(&argv0).is_match(process));
+        assert!(ProcessMatcher::argv0_glob(&format!("*{exe_name}")).is_match(process));
+        assert!(!ProcessMatcher::argv0_glob("*5EClient.exe").is_match(process));
+        let exe = ProcessMatcher::new(MatchField::Exe, MatchKind::Glob, &format!("*{exe_name}"));
+        assert!(exe.unwrap().is_match(process));
+        let cmdline =
+            ProcessMatcher::new(MatchField::Cmdline, MatchKind::Glob, &format!("{argv0}*"));
+        assert!(cmdline.unwrap().is_match(process));
+    }
+
+    #[test]
+    fn missing_target_backs_off() {
+        let now = Instant::now();
+        let mut tracked = Tracked::default();
+        assert!(tracked.scan_due(now));
+
+        let mut intervals = vec![];
+        for _ in 0..5 {
+            tracked.scanned(vec![], now);
+            intervals.push(tracked.backoff.as_secs());
+        }
+        assert_eq!(intervals, [2, 4, 8, 16, 16]);
+        assert!(!tracked.scan_due(now));
+        assert!(tracked.scan_due(now + FULL_SCAN_MAX_INTERVAL));
+
+        tracked.scanned(vec![Pid::from_u32(42)], now);
+        assert!(!tracked.scan_due(now + FULL_SCAN_MAX_INTERVAL));
+        tracked.lost(now);
+        assert!(tracked.scan_due(now));
+        tracked.scanned(vec![], now);
+        assert_eq!(tracked.backoff, FULL_SCAN_INTERVAL);
+    }
+}
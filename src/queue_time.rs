@@ -0,0 +1,328 @@
+//! Queue time from state transitions: search start, match pop and accept.
+//!
+//! A search already running when the program starts has no known start unless the lobby
+//! search timer can be read, user-facing figures leave the queue time out then.
+
+use std::{fs::OpenOptions, io::Write, path::Path, sync::Mutex, time::Duration};
+
+use jiff::{SignedDuration, Timestamp};
+
+use crate::{
+    configure::QueueTimeConfig,
+    state::{self, State, StateEvent},
+    update_status,
+};
+
+const HISTORY_HEADER: &str = "search_start,match_found,accepted,queue_seconds,accept_seconds";
+
+static TIMER: Mutex<QueueTimer> = Mutex::new(QueueTimer::new());
+
+/// Seconds as `3m12s` or `2.1s`
+fn format_seconds(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{seconds:.1}s")
+    } else {
+        let seconds = seconds as u64;
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+fn seconds_between(from: Timestamp, to: Timestamp) -> f64 {
+    to.duration_since(from).as_secs_f64()
+}
+
+/// One match pop, from the start of the search to the accept
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct QueueRecord {
+    search_start: Option<Timestamp>,
+    match_found: Timestamp,
+    accepted: Option<Timestamp>,
+}
+
+impl QueueRecord {
+    fn queue(&self) -> Option<f64> {
+        self.search_start
+            .map(|start| seconds_between(start, self.match_found))
+    }
+
+    fn accept_latency(&self) -> Option<f64> {
+        self.accepted
+            .map(|accepted| seconds_between(self.match_found, accepted))
+    }
+
+    fn csv_line(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        format!(
+            "{},{},{},{},{}",
+            optional(self.search_start.map(|t| t.to_string())),
+            self.match_found,
+            optional(self.accepted.map(|t| t.to_string())),
+            optional(self.queue().map(|s| format!("{s:.1}"))),
+            optional(self.accept_latency().map(|s| format!("{s:.1}"))),
+        )
+    }
+}
+
+impl std::fmt::Display for QueueRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.queue() {
+            Some(queue) => write!(f, "queue {}", format_seconds(queue))?,
+            None => write!(f, "queue unknown")?,
+        }
+        match self.accept_latency() {
+            Some(latency) => write!(f, ", accepted after {}", format_seconds(latency)),
+            None => write!(f, ", not accepted"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct QueueTimer {
+    search_start: Option<Timestamp>,
+    /// Start worked out from the lobby search timer, kept until the search ends
+    read_start: Option<Timestamp>,
+    match_found: Option<Timestamp>,
+    last: Option<QueueRecord>,
+}
+
+impl QueueTimer {
+    const fn new() -> Self {
+        Self {
+            search_start: None,
+            read_start: None,
+            match_found: None,
+            last: None,
+        }
+    }
+
+    /// The earlier one, both are later than the real start
+    fn start(&self) -> Option<Timestamp> {
+        match (self.search_start, self.read_start) {
+            (Some(seen), Some(read)) => Some(seen.min(read)),
+            (seen, read) => seen.or(read),
+        }
+    }
+
+    fn finish(&mut self, accepted: Option<Timestamp>) -> Option<QueueRecord> {
+        let search_start = self.start();
+        self.read_start.take();
+        self.search_start.take();
+        let record = QueueRecord {
+            search_start,
+            match_found: self.match_found.take()?,
+            accepted,
+        };
+        self.last.replace(record);
+        Some(record)
+    }
+
+    /// Returns the record of a match pop that just ended
+    fn on_event(&mut self, event: StateEvent, now: Timestamp) -> Option<QueueRecord> {
+        match (event.from(), event.to()) {
+            // A popup gone without the accept ends its record, CS2 searches again by itself
+            (from, State::Searching) => {
+                // The timer may be read before this event arrives, it belongs to this search
+                // unless a popup ended just now
+                let read_start = self.read_start;
+                let record = self.finish(None);
+                if record.is_none() {
+                    self.read_start = read_start;
+                }
+                // Already searching when the program started, only the lobby timer knows when
+                if from != State::Idle {
+                    self.search_start.replace(now);
+                }
+                record
+            }
+            (_, State::MatchFound) => {
+                if self.match_found.is_none() {
+                    self.match_found.replace(now);
+                }
+                None
+            }
+            (_, State::Accepted) => self.finish(Some(now)),
+            _ => {
+                let record = self.finish(None);
+                self.search_start.take();
+                self.read_start.take();
+                record
+            }
+        }
+    }
+
+    fn status(&self) -> String {
+        match (self.start(), self.match_found, self.last) {
+            (Some(start), None, _) => format!(
+                "queue {}",
+                format_seconds(seconds_between(start, Timestamp::now()))
+            ),
+            (_, Some(_), _) => "match found".to_string(),
+            (None, None, Some(last)) => format!("last {last}"),
+            (None, None, None) => String::new(),
+        }
+    }
+}
+
+/// The running search has no start read from the lobby timer yet
+pub(crate) fn wants_search_timer() -> bool {
+    let timer = TIMER.lock().unwrap();
+    timer.read_start.is_none() && timer.match_found.is_none()
+}
+
+/// The lobby timer showed `elapsed` just now
+pub(crate) fn set_search_elapsed(elapsed: Duration) -> anyhow::Result<()> {
+    let start = Timestamp::now().checked_sub(SignedDuration::try_from(elapsed)?)?;
+    TIMER.lock().unwrap().read_start.replace(start);
+    Ok(())
+}
+
+/// Short text for the status line, empty before the first search
+pub(crate) fn status() -> String {
+    TIMER.lock().unwrap().status()
+}
+
+fn append_history(path: &Path, record: &QueueRecord) -> anyhow::Result<()> {
+    let new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if new {
+        writeln!(file, "{HISTORY_HEADER}")?;
+    }
+    writeln!(file, "{}", record.csv_line())?;
+    Ok(())
+}
+
+pub(crate) fn spawn(config: &QueueTimeConfig, config_file: &str) -> anyhow::Result<()> {
+    // A relative path is next to the config file, an absolute one replaces it
+    let history = config.history().map(|path| {
+        Path::new(config_file)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(path)
+    });
+    let events = state::subscribe();
+    std::thread::Builder::new()
+        .name("queue-time".into())
+        .spawn(move || {
+            for event in events {
+                let now = Timestamp::now();
+                let record = TIMER.lock().unwrap().on_event(event, now);
+                if let Some(record) = record {
+                    log::info!("Match found: {record}");
+                    update_status!(@queue format!("Last {record}"));
+                    if let Some(path) = &history {
+                        append_history(path, &record)
+                            .inspect_err(|e| log::warn!("Write queue history error: {e:?}"))
+                            .ok();
+                    }
+                }
+                // Coming back from a popup, keep showing how it went
+                match (event.from(), event.to()) {
+                    (State::MatchFound | State::Accepted, _) => {}
+                    (State::Idle, State::Searching) => {
+                        update_status!(@queue "Searching, started before this program".to_string());
+                    }
+                    (_, State::Searching) => {
+                        update_status!(@queue format!(
+                            "Searching since {}",
+                            now.to_zoned(jiff::tz::TimeZone::system()).strftime("%H:%M:%S")
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(second: i64) -> Timestamp {
+        Timestamp::from_second(second).unwrap()
+    }
+
+    fn step(timer: &mut QueueTimer, from: State, to: State, second: i64) -> Option<QueueRecord> {
+        timer.on_event(StateEvent::new(from, to), at(second))
+    }
+
+    #[test]
+    fn search_pop_accept() {
+        let mut timer = QueueTimer::new();
+        assert_eq!(
+            step(&mut timer, State::Cs2Running, State::Searching, 100),
+            None
+        );
+        assert_eq!(timer.start(), Some(at(100)));
+        assert_eq!(
+            step(&mut timer, State::Searching, State::MatchFound, 160),
+            None
+        );
+        assert_eq!(timer.status(), "match found");
+
+        let record = step(&mut timer, State::MatchFound, State::Accepted, 165).unwrap();
+        assert_eq!(
+            record,
+            QueueRecord {
+                search_start: Some(at(100)),
+                match_found: at(160),
+                accepted: Some(at(165)),
+            }
+        );
+        assert_eq!(record.to_string(), "queue 1m00s, accepted after 5.0s");
+        assert_eq!(
+            record.csv_line(),
+            "1970-01-01T00:01:40Z,1970-01-01T00:02:40Z,1970-01-01T00:02:45Z,60.0,5.0"
+        );
+
+        // Loading the map ends nothing, the record is already written
+        assert_eq!(step(&mut timer, State::Accepted, State::InGame, 170), None);
+        assert_eq!(timer.status(), format!("last {record}"));
+    }
+
+    #[test]
+    fn failed_pop_searches_again() {
+        let mut timer = QueueTimer::new();
+        step(&mut timer, State::Cs2Running, State::Searching, 100);
+        step(&mut timer, State::Searching, State::MatchFound, 160);
+
+        let record = step(&mut timer, State::MatchFound, State::Searching, 180).unwrap();
+        assert_eq!(record.accepted, None);
+        assert_eq!(record.to_string(), "queue 1m00s, not accepted");
+        // CS2 searches again by itself, the new search starts now
+        assert_eq!(timer.start(), Some(at(180)));
+        assert_eq!(timer.match_found, None);
+    }
+
+    #[test]
+    fn pop_gone_to_lobby() {
+        let mut timer = QueueTimer::new();
+        step(&mut timer, State::Cs2Running, State::Searching, 100);
+        step(&mut timer, State::Searching, State::MatchFound, 130);
+
+        let record = step(&mut timer, State::MatchFound, State::Cs2Running, 150).unwrap();
+        assert_eq!(record.accepted, None);
+        assert_eq!(timer.start(), None);
+    }
+
+    #[test]
+    fn restart_from_idle() {
+        // The program started while CS2 was already searching
+        let mut timer = QueueTimer::new();
+        step(&mut timer, State::Idle, State::Searching, 100);
+        assert_eq!(timer.start(), None);
+        step(&mut timer, State::Searching, State::MatchFound, 160);
+        let record = step(&mut timer, State::MatchFound, State::Accepted, 162).unwrap();
+        assert_eq!(record.queue(), None);
+        assert_eq!(record.to_string(), "queue unknown, accepted after 2.0s");
+
+        // Same, with the lobby timer read before the state change arrived
+        let mut timer = QueueTimer::new();
+        timer.read_start.replace(at(40));
+        step(&mut timer, State::Idle, State::Searching, 100);
+        assert_eq!(timer.start(), Some(at(40)));
+        step(&mut timer, State::Searching, State::MatchFound, 160);
+        let record = step(&mut timer, State::MatchFound, State::Accepted, 162).unwrap();
+        assert_eq!(record.search_start, Some(at(40)));
+    }
+}
@@ -0,0 +1,638 @@
+//! Capture through the xdg-desktop-portal ScreenCast interface and PipeWire,
+//! for Wayland sessions where xcap can not grab the screen reliably.
+
+use std::{
+    os::fd::OwnedFd,
+    sync::{Arc, Mutex, OnceLock, mpsc},
+};
+
+use anyhow::{Context, anyhow};
+use ashpd::desktop::{
+    PersistMode,
+    screencast::{CursorMode, Screencast, SourceType},
+};
+use image::RgbaImage;
+use pipewire as pw;
+use pw::{properties::properties, spa};
+
+use crate::{configure::ScreenCastConfig, types::Point};
+
+static SCREENCAST: OnceLock<ScreenCast> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameFormat {
+    Rgb,
+    // Alpha channel is ignored
+    Rgbx,
+    Bgrx,
+}
+
+impl FrameFormat {
+    fn from_video_format(format: spa::param::video::VideoFormat) -> Option<Self> {
+        use spa::param::video::VideoFormat;
+        match format {
+            VideoFormat::RGB => Some(Self::Rgb),
+            VideoFormat::RGBx | VideoFormat::RGBA => Some(Self::Rgbx),
+            VideoFormat::BGRx | VideoFormat::BGRA => Some(Self::Bgrx),
+            _ => None,
+        }
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgb => 3,
+            Self::Rgbx | Self::Bgrx => 4,
+        }
+    }
+}
+
+/// Latest frame received from PipeWire, converted on demand
+struct RawFrame {
+    width: u32,
+    height: u32,
+    stride: usize,
+    format: FrameFormat,
+    data: Vec<u8>,
+}
+
+impl RawFrame {
+    fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> anyhow::Result<RgbaImage> {
+        if x + width > self.width || y + height > self.height {
+            return Err(anyhow!(
+                "Crop ({x}, {y}) {width}x{height} is outside of frame {}x{}",
+                self.width,
+                self.height
+            ));
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let mut image = RgbaImage::new(width, height);
+
+        for (dy, row) in (y..y + height).enumerate() {
+            let start = row as usize * self.stride + x as usize * bpp;
+            let line = self
+                .data
+                .get(start..start + width as usize * bpp)
+                .ok_or_else(|| anyhow!("Frame buffer is shorter than expected"))?;
+            for (dx, pixel) in line.chunks_exact(bpp).enumerate() {
+                let rgba = match self.format {
+                    FrameFormat::Rgb | FrameFormat::Rgbx => [pixel[0], pixel[1], pixel[2], 255],
+                    FrameFormat::Bgrx => [pixel[2], pixel[1], pixel[0], 255],
+                };
+                image.put_pixel(dx as u32, dy as u32, image::Rgba(rgba));
+            }
+        }
+        Ok(image)
+    }
+}
+
+pub(crate) struct ScreenCast {
+    frame: Arc<Mutex<Option<RawFrame>>>,
+    // Position and size of the streamed monitor in the compositor's logical coordinates
+    bounds: Point,
+}
+
+impl ScreenCast {
+    /// Capture `region` (screen coordinates) from the latest frame
+    pub(crate) fn capture(&self, region: Point) -> anyhow::Result<RgbaImage> {
+        region.check_within(&self.bounds)?;
+        let frame = self.frame.lock().unwrap();
+        let frame = frame
+            .as_ref()
+            .ok_or_else(|| anyhow!("No frame received from screencast yet"))?;
+
+        // Frame is in physical pixels, which differs from the logical size on scaled outputs
+        let scale_x = frame.width as f32 / self.bounds.width() as f32;
+        let scale_y = frame.height as f32 / self.bounds.height() as f32;
+        let x = ((region.x() - self.bounds.x()) as f32 * scale_x) as u32;
+        let y = ((region.y() - self.bounds.y()) as f32 * scale_y) as u32;
+        let width = ((region.width() as f32 * scale_x) as u32).min(frame.width - x);
+        let height = ((region.height() as f32 * scale_y) as u32).min(frame.height - y);
+
+        let image = frame.crop(x, y, width, height)?;
+        if image.dimensions() == (region.width() as u32, region.height() as u32) {
+            return Ok(image);
+        }
+        Ok(image::imageops::resize(
+            &image,
+            region.width() as u32,
+            region.height() as u32,
+            image::imageops::FilterType::Nearest,
+        ))
+    }
+}
+
+pub(crate) fn get() -> Option<&'static ScreenCast> {
+    SCREENCAST.get()
+}
+
+async fn open_portal(
+    restore_token: Option<&str>,
+) -> anyhow::Result<(ashpd::desktop::screencast::Stream, OwnedFd, Option<String>)> {
+    let proxy = Screencast::new().await?;
+    let session = proxy.create_session().await?;
+    proxy
+        .select_sources(
+            &session,
+            CursorMode::Hidden,
+            SourceType::Monitor.into(),
+            false,
+            restore_token,
+            PersistMode::ExplicitlyRevoked,
+        )
+        .await?;
+
+    let response = proxy.start(&session, None).await?.response()?;
+    let stream = response
+        .streams()
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow!("No stream selected"))?;
+    let fd = proxy.open_pipe_wire_remote(&session).await?;
+
+    Ok((stream, fd, response.restore_token().map(str::to_string)))
+}
+
+fn format_params() -> anyhow::Result<Vec<u8>> {
+    use spa::param::{format::FormatProperties, video::VideoFormat};
+
+    let obj = spa::pod::object!(
+        spa::utils::SpaTypes::ObjectParamFormat,
+        spa::param::ParamType::EnumFormat,
+        spa::pod::property!(
+            FormatProperties::MediaType,
+            Id,
+            spa::param::format::MediaType::Video
+        ),
+        spa::pod::property!(
+            FormatProperties::MediaSubtype,
+            Id,
+            spa::param::format::MediaSubtype::Raw
+        ),
+        spa::pod::property!(
+            FormatProperties::VideoFormat,
+            Choice,
+            Enum,
+            Id,
+            VideoFormat::BGRx,
+            VideoFormat::BGRx,
+            VideoFormat::BGRA,
+            VideoFormat::RGBx,
+            VideoFormat::RGBA,
+            VideoFormat::RGB,
+        ),
+        // Only the latest frame is used, no need to stream fast
+        spa::pod::property!(
+            FormatProperties::VideoFramerate,
+            Choice,
+            Range,
+            Fraction,
+            spa::utils::Fraction { num: 5, denom: 1 },
+            spa::utils::Fraction { num: 0, denom: 1 },
+            spa::utils::Fraction { num: 30, denom: 1 }
+        ),
+    );
+
+    Ok(spa::pod::serialize::PodSerializer::serialize(
+        std::io::Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(obj),
+    )
+    .map_err(|e| anyhow!("Serialize format params: {e:?}"))?
+    .0
+    .into_inner())
+}
+
+struct StreamData {
+    format: spa::param::video::VideoInfoRaw,
+    frame: Arc<Mutex<Option<RawFrame>>>,
+}
+
+/// Receive frames of `node_id` until the process exits. `remote` is the PipeWire connection the
+/// portal opened, `None` connects to the local daemon
+fn pipewire_thread(
+    remote: Option<OwnedFd>,
+    node_id: u32,
+    frame: Arc<Mutex<Option<RawFrame>>>,
+    ready: mpsc::Sender<anyhow::Result<()>>,
+) -> anyhow::Result<()> {
+    pw::init();
+
+    let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+    let context = pw::context::ContextRc::new(&mainloop, None)?;
+    let core = match remote {
+        Some(fd) => context.connect_fd_rc(fd, None)?,
+        None => context.connect_rc(None)?,
+    };
+
+    let stream = pw::stream::StreamBox::new(
+        &core,
+        "cs2-auto-accept",
+        properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )?;
+
+    let _listener = stream
+        .add_local_listener_with_user_data(StreamData {
+            format: Default::default(),
+            frame,
+        })
+        .state_changed(|_, _, old, new| log::debug!("Screencast stream {old:?} -> {new:?}"))
+        .param_changed(|_, data, id, param| {
+            let Some(param) = param else {
+                return;
+            };
+            if id != spa::param::ParamType::Format.as_raw() {
+                return;
+            }
+            if let Err(e) = data.format.parse(param) {
+                log::warn!("Parse screencast format error: {e:?}");
+                return;
+            }
+            log::debug!(
+                "Screencast format: {:?} {}x{}",
+                data.format.format(),
+                data.format.size().width,
+                data.format.size().height
+            );
+        })
+        .process(|stream, data| {
+            let Some(mut buffer) = stream.dequeue_buffer() else {
+                return;
+            };
+            let Some(format) = FrameFormat::from_video_format(data.format.format()) else {
+                return;
+            };
+            let size = data.format.size();
+            let Some(raw) = buffer.datas_mut().first_mut() else {
+                return;
+            };
+            let offset = raw.chunk().offset() as usize;
+            let chunk_size = raw.chunk().size() as usize;
+            let stride = match raw.chunk().stride() {
+                stride if stride > 0 => stride as usize,
+                _ => size.width as usize * format.bytes_per_pixel(),
+            };
+            let Some(bytes) = raw.data() else {
+                return;
+            };
+            let Some(bytes) = bytes.get(offset..offset + chunk_size) else {
+                return;
+            };
+
+            data.frame.lock().unwrap().replace(RawFrame {
+                width: size.width,
+                height: size.height,
+                stride,
+                format,
+                data: bytes.to_vec(),
+            });
+        })
+        .register()?;
+
+    let values = format_params()?;
+    let mut params =
+        [spa::pod::Pod::from_bytes(&values).ok_or_else(|| anyhow!("Invalid format params"))?];
+
+    stream.connect(
+        spa::utils::Direction::Input,
+        Some(node_id),
+        pw::stream::StreamFlags::AUTOCONNECT | pw::stream::StreamFlags::MAP_BUFFERS,
+        &mut params,
+    )?;
+
+    ready.send(Ok(())).ok();
+    mainloop.run();
+    Ok(())
+}
+
+/// Ask the portal for a monitor stream and start receiving frames.
+///
+/// The restore token is kept in `token-file`, the user only has to approve once.
+pub(crate) fn init(config: &ScreenCastConfig) -> anyhow::Result<()> {
+    if SCREENCAST.get().is_some() {
+        return Ok(());
+    }
+
+    let token = std::fs::read_to_string(config.token_file())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let (stream, fd, new_token) = futures_lite::future::block_on(open_portal(token.as_deref()))
+        .context("Open screencast portal")?;
+
+    if let Some(new_token) = new_token {
+        std::fs::write(config.token_file(), new_token)
+            .inspect_err(|e| log::warn!("Save screencast token error: {e:?}"))
+            .ok();
+    }
+
+    let (x, y) = stream.position().unwrap_or_default();
+    let (width, height) = stream
+        .size()
+        .ok_or_else(|| anyhow!("Screencast stream has no size"))?;
+    let node_id = stream.pipe_wire_node_id();
+    log::info!("Screencast node {node_id}: ({x}, {y}) {width}x{height}");
+
+    let frame = Arc::new(Mutex::new(None));
+    let (ready_tx, ready_rx) = mpsc::channel();
+    std::thread::Builder::new().name("pipewire".into()).spawn({
+        let frame = frame.clone();
+        let ready_tx = ready_tx.clone();
+        move || {
+            if let Err(e) = pipewire_thread(Some(fd), node_id, frame, ready_tx.clone()) {
+                log::error!("PipeWire thread error: {e:?}");
+                ready_tx.send(Err(e)).ok();
+            }
+        }
+    })?;
+    drop(ready_tx);
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("PipeWire thread exited"))??;
+
+    SCREENCAST
+        .set(ScreenCast {
+            frame,
+            bounds: Point::new(x, y, x + width, y + height),
+        })
+        .ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// 4x2 frame, each pixel is `[x, y, 10 * x + y]` in `format` order, rows padded to `stride`
+    fn frame(format: FrameFormat, stride: usize) -> RawFrame {
+        let bpp = format.bytes_per_pixel();
+        let mut data = vec![0xee; stride * 2];
+        for y in 0..2u8 {
+            for x in 0..4u8 {
+                let start = y as usize * stride + x as usize * bpp;
+                let rgb = [x, y, 10 * x + y];
+                let pixel = match format {
+                    FrameFormat::Bgrx => [rgb[2], rgb[1], rgb[0]],
+                    FrameFormat::Rgb | FrameFormat::Rgbx => rgb,
+                };
+                data[start..start + 3].copy_from_slice(&pixel);
+            }
+        }
+        RawFrame {
+            width: 4,
+            height: 2,
+            stride,
+            format,
+            data,
+        }
+    }
+
+    #[test]
+    fn crop_formats() {
+        for (format, stride) in [
+            (FrameFormat::Rgb, 12),
+            (FrameFormat::Rgb, 16),
+            (FrameFormat::Rgbx, 16),
+            (FrameFormat::Bgrx, 20),
+        ] {
+            let image = frame(format, stride).crop(1, 1, 3, 1).unwrap();
+            assert_eq!(image.dimensions(), (3, 1), "{format:?}");
+            for x in 0..3 {
+                let source = x as u8 + 1;
+                assert_eq!(
+                    image.get_pixel(x, 0).0,
+                    [source, 1, 10 * source + 1, 255],
+                    "{format:?} stride {stride}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn crop_out_of_frame() {
+        let raw = frame(FrameFormat::Bgrx, 16);
+        assert!(raw.crop(2, 0, 3, 1).is_err());
+        assert!(raw.crop(0, 1, 1, 2).is_err());
+
+        let mut short = frame(FrameFormat::Bgrx, 16);
+        short.data.truncate(20);
+        assert!(short.crop(0, 1, 4, 1).is_err());
+    }
+
+    #[test]
+    fn capture_scaled_output() {
+        // Logical 2x1 at (100, 50), the frame has twice as many pixels
+        let screencast = ScreenCast {
+            frame: Arc::new(Mutex::new(Some(frame(FrameFormat::Rgbx, 16)))),
+            bounds: Point::new(100, 50, 102, 51),
+        };
+        // Each logical pixel is 2x2 frame pixels, nearest keeps the bottom right one
+        let image = screencast.capture(Point::new(101, 50, 102, 51)).unwrap();
+        assert_eq!(image.dimensions(), (1, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [3, 1, 31, 255]);
+
+        let image = screencast.capture(Point::new(100, 50, 102, 51)).unwrap();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [1, 1, 11, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [3, 1, 31, 255]);
+
+        assert!(screencast.capture(Point::new(99, 50, 101, 51)).is_err());
+    }
+
+    const SOURCE_WIDTH: u32 = 64;
+    const SOURCE_HEIGHT: u32 = 32;
+
+    fn serialize(obj: spa::pod::Object) -> Vec<u8> {
+        spa::pod::serialize::PodSerializer::serialize(
+            std::io::Cursor::new(Vec::new()),
+            &spa::pod::Value::Object(obj),
+        )
+        .unwrap()
+        .0
+        .into_inner()
+    }
+
+    /// Video source node driven by a timer, pixel (x, y) is BGRx `[y, x, 200, 255]`.
+    /// Sends its node id once the stream is ready to be linked
+    fn test_source(node: mpsc::Sender<u32>) -> anyhow::Result<()> {
+        use spa::param::{format::FormatProperties, video::VideoFormat};
+
+        pw::init();
+        let mainloop = pw::main_loop::MainLoopRc::new(None)?;
+        let context = pw::context::ContextRc::new(&mainloop, None)?;
+        let core = context.connect_rc(None)?;
+        let stream = pw::stream::StreamRc::new(
+            core,
+            "cs2-auto-accept-test-source",
+            properties! {
+                *pw::keys::MEDIA_TYPE => "Video",
+                *pw::keys::MEDIA_CLASS => "Video/Source",
+                *pw::keys::MEDIA_ROLE => "Screen",
+            },
+        )?;
+
+        let stride = SOURCE_WIDTH as usize * 4;
+        let size = stride * SOURCE_HEIGHT as usize;
+        let _listener = stream
+            .add_local_listener_with_user_data(node)
+            .state_changed(|stream, node, _, new| {
+                if matches!(new, pw::stream::StreamState::Paused) {
+                    node.send(stream.node_id()).ok();
+                }
+            })
+            .param_changed(move |stream, _, id, param| {
+                if param.is_none() || id != spa::param::ParamType::Format.as_raw() {
+                    return;
+                }
+                let buffers = serialize(spa::pod::Object {
+                    type_: spa::utils::SpaTypes::ObjectParamBuffers.as_raw(),
+                    id: spa::param::ParamType::Buffers.as_raw(),
+                    properties: [
+                        (spa::sys::SPA_PARAM_BUFFERS_buffers, 4),
+                        (spa::sys::SPA_PARAM_BUFFERS_blocks, 1),
+                        (spa::sys::SPA_PARAM_BUFFERS_size, size as i32),
+                        (spa::sys::SPA_PARAM_BUFFERS_stride, stride as i32),
+                    ]
+                    .map(|(key, value)| spa::pod::Property::new(key, spa::pod::Value::Int(value)))
+                    .to_vec(),
+                });
+                let mut params = [spa::pod::Pod::from_bytes(&buffers).unwrap()];
+                stream.update_params(&mut params).unwrap();
+            })
+            .process(move |stream, _| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let Some(data) = buffer.datas_mut().first_mut() else {
+                    return;
+                };
+                let Some(bytes) = data.data().and_then(|bytes| bytes.get_mut(..size)) else {
+                    return;
+                };
+                for (i, pixel) in bytes.chunks_exact_mut(4).enumerate() {
+                    let (x, y) = (i % SOURCE_WIDTH as usize, i / SOURCE_WIDTH as usize);
+                    pixel.copy_from_slice(&[y as u8, x as u8, 200, 255]);
+                }
+                let chunk = data.chunk_mut();
+                *chunk.offset_mut() = 0;
+                *chunk.stride_mut() = stride as i32;
+                *chunk.size_mut() = size as u32;
+            })
+            .register()?;
+
+        let format = serialize(spa::pod::object!(
+            spa::utils::SpaTypes::ObjectParamFormat,
+            spa::param::ParamType::EnumFormat,
+            spa::pod::property!(
+                FormatProperties::MediaType,
+                Id,
+                spa::param::format::MediaType::Video
+            ),
+            spa::pod::property!(
+                FormatProperties::MediaSubtype,
+                Id,
+                spa::param::format::MediaSubtype::Raw
+            ),
+            spa::pod::property!(FormatProperties::VideoFormat, Id, VideoFormat::BGRx),
+            spa::pod::property!(
+                FormatProperties::VideoSize,
+                Rectangle,
+                spa::utils::Rectangle {
+                    width: SOURCE_WIDTH,
+                    height: SOURCE_HEIGHT
+                }
+            ),
+            spa::pod::property!(
+                FormatProperties::VideoFramerate,
+                Fraction,
+                spa::utils::Fraction { num: 10, denom: 1 }
+            ),
+        ));
+        let mut params = [spa::pod::Pod::from_bytes(&format).unwrap()];
+        stream.connect(
+            spa::utils::Direction::Output,
+            None,
+            pw::stream::StreamFlags::DRIVER | pw::stream::StreamFlags::MAP_BUFFERS,
+            &mut params,
+        )?;
+
+        // A driver produces a frame only when asked to
+        let timer = mainloop.loop_().add_timer({
+            let stream = stream.clone();
+            move |_| {
+                stream.trigger_process().ok();
+            }
+        });
+        timer
+            .update_timer(
+                Some(Duration::from_millis(1)),
+                Some(Duration::from_millis(100)),
+            )
+            .into_result()?;
+
+        mainloop.run();
+        Ok(())
+    }
+
+    #[test]
+    fn local_stream() {
+        let (node_tx, node_rx) = mpsc::channel();
+        std::thread::spawn(move || test_source(node_tx).unwrap());
+        let node_id = node_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Test source did not start, is PipeWire running?");
+
+        let frame = Arc::new(Mutex::new(None));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        std::thread::spawn({
+            let frame = frame.clone();
+            move || pipewire_thread(None, node_id, frame, ready_tx).unwrap()
+        });
+        ready_rx.recv().unwrap().unwrap();
+
+        let instant = Instant::now();
+        while frame.lock().unwrap().is_none() {
+            assert!(
+                instant.elapsed() < Duration::from_secs(10),
+                "No frame from the test source, is a session manager linking nodes?"
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let screencast = ScreenCast {
+            frame,
+            bounds: Point::new(0, 0, SOURCE_WIDTH as i32, SOURCE_HEIGHT as i32),
+        };
+        let image = screencast.capture(Point::new(10, 5, 14, 7)).unwrap();
+        assert_eq!(image.dimensions(), (4, 2));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            assert_eq!(pixel.0, [200, 10 + x as u8, 5 + y as u8, 255], "({x}, {y})");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a desktop session with xdg-desktop-portal and PipeWire, asks to share a monitor"]
+    fn portal_stream() {
+        let token_file = std::env::temp_dir().join("cs2-auto-accept-screencast.token");
+        let config: ScreenCastConfig =
+            toml::from_str(&format!("enabled = true\ntoken-file = {token_file:?}")).unwrap();
+        init(&config).unwrap();
+        let screencast = get().unwrap();
+
+        let instant = Instant::now();
+        while screencast.frame.lock().unwrap().is_none() {
+            assert!(
+                instant.elapsed() < Duration::from_secs(10),
+                "No frame from PipeWire"
+            );
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let bounds = screencast.bounds;
+        let region = Point::new(bounds.x(), bounds.y(), bounds.x() + 32, bounds.y() + 16);
+        assert_eq!(screencast.capture(region).unwrap().dimensions(), (32, 16));
+    }
+}
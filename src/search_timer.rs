@@ -0,0 +1,367 @@
+//! Reads the elapsed search time the CS2 lobby shows in the top right corner.
+//!
+//! Glyphs are cut from a light-on-dark strip and compared with per-glyph bitmaps, which
+//! `search-timer extract` cuts from a saved strip of a known time. The bitmaps only fit
+//! the resolution they were taken at.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+use image::{GrayImage, Luma};
+
+use crate::{
+    ImageType, PointOption,
+    configure::{Configure, SearchTimerConfig},
+    primary_monitor, screen_cap,
+    types::{Point, Region},
+    window::GameArea,
+};
+
+/// Pixels at least this bright are part of a glyph
+const INK_LUMA: u32 = 150;
+/// Share of pixels that has to agree with the best bitmap
+const MIN_SCORE: f32 = 0.85;
+const COLON_NAME: &str = "colon";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Bitmap {
+    width: usize,
+    height: usize,
+    ink: Vec<bool>,
+}
+
+impl Bitmap {
+    fn from_image(image: &ImageType) -> Self {
+        let (width, height) = image.dimensions();
+        Self {
+            width: width as usize,
+            height: height as usize,
+            ink: image
+                .pixels()
+                .map(|p| {
+                    let [r, g, b] = p.0;
+                    (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000 >= INK_LUMA
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.ink[y * self.width + x]
+    }
+
+    fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            ink: (y..y + height)
+                .flat_map(|row| (x..x + width).map(move |column| self.get(column, row)))
+                .collect(),
+        }
+    }
+
+    /// Glyphs from left to right, split at columns without ink and trimmed to their ink
+    fn segment(&self) -> Vec<Self> {
+        let column_ink = |x: usize| (0..self.height).any(|y| self.get(x, y));
+        let mut glyphs = vec![];
+        let mut start = None;
+        for x in 0..=self.width {
+            match (start, x < self.width && column_ink(x)) {
+                (None, true) => start = Some(x),
+                (Some(left), false) => {
+                    start = None;
+                    let row_ink = |y: usize| (left..x).any(|column| self.get(column, y));
+                    let top = (0..self.height).find(|y| row_ink(*y)).unwrap_or_default();
+                    let bottom = (0..self.height).rfind(|y| row_ink(*y)).unwrap_or_default();
+                    glyphs.push(self.crop(left, top, x - left, bottom - top + 1));
+                }
+                _ => {}
+            }
+        }
+        glyphs
+    }
+
+    /// Share of agreeing pixels, glyphs of a clearly different size do not match at all
+    fn score(&self, other: &Self) -> f32 {
+        if self.width.abs_diff(other.width) > 1 || self.height.abs_diff(other.height) > 1 {
+            return 0.0;
+        }
+        let (width, height) = (self.width.max(other.width), self.height.max(other.height));
+        let same = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| self.get(*x, *y) == other.get(*x, *y))
+            .count();
+        same as f32 / (width * height) as f32
+    }
+
+    fn to_image(&self) -> GrayImage {
+        GrayImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            Luma([if self.get(x as usize, y as usize) {
+                255
+            } else {
+                0
+            }])
+        })
+    }
+}
+
+fn glyph_name(glyph: char) -> anyhow::Result<String> {
+    match glyph {
+        '0'..='9' => Ok(glyph.to_string()),
+        ':' => Ok(COLON_NAME.to_string()),
+        _ => Err(anyhow!(
+            "Only digits and ':' can be extracted, got {glyph:?}"
+        )),
+    }
+}
+
+/// `m:ss` or `h:mm:ss`
+fn parse_elapsed(text: &str) -> Option<Duration> {
+    let parts = text
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let seconds = match parts.as_slice() {
+        [minutes, seconds] if *seconds < 60 => minutes * 60 + seconds,
+        [hours, minutes, seconds] if *minutes < 60 && *seconds < 60 => {
+            hours * 3600 + minutes * 60 + seconds
+        }
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+/// Same layout as the lobby shows it
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{hours}:{:02}:{:02}", seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// Bitmaps of `0`-`9` and `:`
+pub(crate) struct Digits {
+    glyphs: Vec<(char, Bitmap)>,
+}
+
+impl Digits {
+    /// Every digit is needed, the colon is optional
+    pub(crate) fn load(directory: &str) -> anyhow::Result<Self> {
+        let mut glyphs = vec![];
+        for glyph in ('0'..='9').chain([':']) {
+            let path = Path::new(directory).join(format!("{}.png", glyph_name(glyph)?));
+            if glyph == ':' && !path.exists() {
+                continue;
+            }
+            let image = image::ImageReader::open(&path)
+                .with_context(|| format!("Open digit bitmap {path:?}, run search-timer extract"))?
+                .decode()?
+                .into_rgb8();
+            glyphs.push((glyph, Bitmap::from_image(&image)));
+        }
+        Ok(Self { glyphs })
+    }
+
+    fn recognise(&self, glyph: &Bitmap) -> Option<char> {
+        self.glyphs
+            .iter()
+            .map(|(c, bitmap)| (*c, bitmap.score(glyph)))
+            .filter(|(_, score)| *score >= MIN_SCORE)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(c, _)| c)
+    }
+
+    fn read_text(&self, strip: &Bitmap) -> Option<String> {
+        let glyphs = strip.segment();
+        if glyphs.is_empty() {
+            return None;
+        }
+        glyphs.iter().map(|glyph| self.recognise(glyph)).collect()
+    }
+
+    pub(crate) fn read(&self, strip: &ImageType) -> Option<Duration> {
+        parse_elapsed(&self.read_text(&Bitmap::from_image(strip))?)
+    }
+}
+
+/// Elapsed search time from the screen, `None` if the timer can not be read
+pub(crate) fn read_screen(
+    digits: &Digits,
+    area: &GameArea,
+    config: &SearchTimerConfig,
+) -> anyhow::Result<Option<Duration>> {
+    let (_, strip) = screen_cap(area.point(Region::Geometry(config.region()))?, false)?;
+    Ok(digits.read(&strip))
+}
+
+fn load_config(config: &String) -> Configure {
+    Configure::load(config)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default()
+}
+
+/// Save the timer strip of the primary monitor, input for `extract`
+pub(crate) fn capture(config: &String, output: &str) -> anyhow::Result<()> {
+    let config = load_config(config);
+    let point = PointOption::Area(
+        Point::from_monitor(&primary_monitor()?)?,
+        Region::Geometry(config.search_timer().region()),
+    );
+    let (_, strip) = screen_cap(point, false)?;
+    strip.save(output)?;
+    println!("Saved {output}");
+    Ok(())
+}
+
+/// Cut the glyphs of a strip showing `text` into one bitmap per glyph
+pub(crate) fn extract(file: &str, text: &str, output: &str) -> anyhow::Result<()> {
+    let strip = image::ImageReader::open(file)?.decode()?.into_rgb8();
+    let glyphs = Bitmap::from_image(&strip).segment();
+    let chars = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    if glyphs.len() != chars.len() {
+        return Err(anyhow!(
+            "Found {} glyphs in {file} but {text:?} has {}, crop the strip to the timer",
+            glyphs.len(),
+            chars.len()
+        ));
+    }
+
+    std::fs::create_dir_all(output)?;
+    for (glyph, bitmap) in chars.iter().zip(&glyphs) {
+        let path = PathBuf::from(output).join(format!("{}.png", glyph_name(*glyph)?));
+        bitmap.to_image().save(&path)?;
+        println!("{glyph} {}x{} -> {path:?}", bitmap.width, bitmap.height);
+    }
+    Ok(())
+}
+
+/// Read saved strips, a file or every file of a directory
+pub(crate) fn read_files(config: &String, path: &str) -> anyhow::Result<()> {
+    let config = load_config(config);
+    let digits = Digits::load(config.search_timer().digits())?;
+    let files = match Path::new(path).is_dir() {
+        true => std::fs::read_dir(path)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        false => vec![PathBuf::from(path)],
+    };
+
+    let mut read = 0;
+    for file in &files {
+        let strip = image::ImageReader::open(file)?.decode()?.into_rgb8();
+        match digits.read(&strip) {
+            Some(elapsed) => {
+                read += 1;
+                println!("{file:?} {}", format_elapsed(elapsed));
+            }
+            None => println!("{file:?} not recognised"),
+        }
+    }
+    println!("Read/Total: {read}/{}", files.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Strips use a 5x7 font scaled twice, light on dark like the lobby. `digits` is what
+    // `search-timer extract strips/all-glyphs.png 01234:56789` cut from them
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/search-timer");
+
+    fn bitmap(rows: &[&str]) -> Bitmap {
+        Bitmap {
+            width: rows[0].len(),
+            height: rows.len(),
+            ink: rows
+                .iter()
+                .flat_map(|row| row.bytes().map(|b| b == b'#'))
+                .collect(),
+        }
+    }
+
+    fn strip(name: &str) -> ImageType {
+        image::ImageReader::open(format!("{FIXTURES}/strips/{name}"))
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_rgb8()
+    }
+
+    fn digits() -> Digits {
+        Digits::load(&format!("{FIXTURES}/digits")).unwrap()
+    }
+
+    #[test]
+    fn segment_splits_and_trims() {
+        let glyphs = bitmap(&[
+            "..........",
+            ".##...#...",
+            ".##.......",
+            "......#.##",
+            "........#.",
+        ])
+        .segment();
+        assert_eq!(
+            glyphs,
+            [
+                bitmap(&["##", "##"]),
+                bitmap(&["#", ".", "#"]),
+                bitmap(&["##", "#."]),
+            ]
+        );
+        assert!(bitmap(&["...", "..."]).segment().is_empty());
+    }
+
+    #[test]
+    fn segment_fixture_strip() {
+        let glyphs = Bitmap::from_image(&strip("all-glyphs.png")).segment();
+        assert_eq!(glyphs.len(), "01234:56789".len());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_elapsed("2:05"), Some(Duration::from_secs(125)));
+        assert_eq!(parse_elapsed("0:00"), Some(Duration::ZERO));
+        assert_eq!(parse_elapsed("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_elapsed("2:60"), None);
+        assert_eq!(parse_elapsed("1:60:00"), None);
+        assert_eq!(parse_elapsed("205"), None);
+        assert_eq!(parse_elapsed("1:2:3:4"), None);
+        assert_eq!(parse_elapsed("1::05"), None);
+    }
+
+    #[test]
+    fn read_strips() {
+        let digits = digits();
+        for (name, seconds) in [
+            ("2m05s.png", 125),
+            ("1h02m03s.png", 3723),
+            ("noisy-12m34s.png", 754),
+        ] {
+            assert_eq!(
+                digits.read(&strip(name)),
+                Some(Duration::from_secs(seconds)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn blank_strip_is_not_read() {
+        assert_eq!(digits().read(&ImageType::new(40, 12)), None);
+    }
+
+    #[test]
+    fn format_hours() {
+        assert_eq!(format_elapsed(Duration::from_secs(125)), "2:05");
+        assert_eq!(format_elapsed(Duration::from_secs(3723)), "1:02:03");
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    sync::{Mutex, mpsc},
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+static SUBSCRIBERS: Mutex<Vec<mpsc::Sender<StateEvent>>> = Mutex::new(Vec::new());
+
+/// The accept countdown is 20s, a popup state lasting longer missed how the match went
+const POPUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Config names match the `Display` names
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub(crate) enum State {
+    /// Neither CS2 nor 5E is running
+    #[default]
+    Idle,
+    /// CS2 is in the menu, not searching
+    #[serde(rename = "CS2Running")]
+    Cs2Running,
+    Searching,
+    /// Accept button is on the screen
+    MatchFound,
+    /// Accept button is clicked
+    Accepted,
+    InGame,
+    /// 5E client is waiting for the match, CS2 is not started yet
+    #[serde(rename = "5EReady")]
+    E5Ready,
+}
+
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Idle => "Idle",
+            Self::Cs2Running => "CS2Running",
+            Self::Searching => "Searching",
+            Self::MatchFound => "MatchFound",
+            Self::Accepted => "Accepted",
+            Self::InGame => "InGame",
+            Self::E5Ready => "5EReady",
+        })
+    }
+}
+
+/// What the main loop saw in this iteration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Observation {
+    NoProcess,
+    /// CS2 went away while Game State Integration still reported a match in progress
+    GameLost,
+    /// 5E client is running without CS2
+    E5Waiting,
+    /// 5E client is running with CS2
+    E5Playing,
+    /// CS2 is running, no searching indicator in the lobby
+    Cs2Idle,
+    /// CS2 lobby shows the searching indicator
+    Cs2Searching,
+    /// Game State Integration or the console log reports a map is loaded
+    Cs2Playing,
+    /// Game State Integration reports the main menu
+    Cs2Menu,
+    /// Game State Integration reports the main menu, the match before was not over
+    MatchLeft,
+    AcceptFound,
+    Clicked,
+    /// Accept button went away during `accept-delay`, taken as accepted by hand
+    HandledByUser,
+    /// Back in the lobby because someone did not accept
+    AcceptFailed,
+    /// Reconnect button in the main menu is clicked
+    Reconnected,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StateEvent {
+    from: State,
+    to: State,
+}
+
+impl StateEvent {
+    pub(crate) fn new(from: State, to: State) -> Self {
+        Self { from, to }
+    }
+
+    pub(crate) fn from(&self) -> State {
+        self.from
+    }
+
+    pub(crate) fn to(&self) -> State {
+        self.to
+    }
+}
+
+/// Receive every state transition from now on
+pub(crate) fn subscribe() -> mpsc::Receiver<StateEvent> {
+    let (s, r) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(s);
+    r
+}
+
+fn emit(event: StateEvent) {
+    SUBSCRIBERS
+        .lock()
+        .unwrap()
+        .retain(|s| s.send(event).is_ok());
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StateMachine {
+    state: State,
+    /// When `state` was entered, `None` before the first change
+    entered: Option<Instant>,
+    /// CS2 went away in the middle of a match, e.g. it crashed
+    interrupted_game: bool,
+}
+
+impl StateMachine {
+    pub(crate) fn state(&self) -> State {
+        self.state
+    }
+
+    fn popup_timed_out(&self) -> bool {
+        self.entered.is_some_and(|at| at.elapsed() >= POPUP_TIMEOUT)
+    }
+
+    fn next(&self, observation: Observation) -> State {
+        match (self.state, observation) {
+            (_, Observation::NoProcess | Observation::GameLost) => State::Idle,
+            // Lobby indicator stays visible behind the popup, also once it is accepted
+            (State::MatchFound | State::Accepted, Observation::Cs2Searching)
+                if !self.popup_timed_out() =>
+            {
+                self.state
+            }
+            // GSI reports the menu until the accepted match has loaded the map
+            (State::Accepted, Observation::Cs2Menu | Observation::MatchLeft)
+                if !self.popup_timed_out() =>
+            {
+                State::Accepted
+            }
+            (_, Observation::E5Waiting) => State::E5Ready,
+            (_, Observation::E5Playing) => State::InGame,
+            // Lobby indicator disappears once the accepted match is loading, without GSI
+            // nothing tells the end of the match apart
+            (State::Accepted | State::InGame, Observation::Cs2Idle) => State::InGame,
+            (_, Observation::Cs2Idle | Observation::Cs2Menu | Observation::MatchLeft) => {
+                State::Cs2Running
+            }
+            (_, Observation::Cs2Searching) => State::Searching,
+            (_, Observation::Cs2Playing) => State::InGame,
+            (_, Observation::AcceptFound) => State::MatchFound,
+            (_, Observation::Clicked | Observation::HandledByUser) => State::Accepted,
+            (_, Observation::AcceptFailed) => State::Cs2Running,
+            (_, Observation::Reconnected) => State::InGame,
+        }
+    }
+
+    pub(crate) fn interrupted_game(&self) -> bool {
+        self.interrupted_game
+    }
+
+    pub(crate) fn observe(&mut self, observation: Observation) -> State {
+        match observation {
+            Observation::GameLost | Observation::MatchLeft if self.state == State::InGame => {
+                self.interrupted_game = true;
+            }
+            // A new match or the old one is back, nothing left to reconnect to
+            Observation::Cs2Searching
+            | Observation::Cs2Playing
+            | Observation::AcceptFound
+            | Observation::Reconnected => {
+                self.interrupted_game = false;
+            }
+            _ => {}
+        }
+        let next = self.next(observation);
+        if next != self.state {
+            log::info!("State: {} -> {next} ({observation:?})", self.state);
+            emit(StateEvent::new(self.state, next));
+            self.state = next;
+            self.entered.replace(Instant::now());
+        }
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Observation::*, *};
+
+    fn run(observations: &[Observation]) -> StateMachine {
+        let mut machine = StateMachine::default();
+        for observation in observations {
+            machine.observe(*observation);
+        }
+        machine
+    }
+
+    #[test]
+    fn quit_after_match() {
+        let mut machine = run(&[Cs2Searching, AcceptFound, Clicked, Cs2Playing, Cs2Menu]);
+        assert_eq!(machine.state(), State::Cs2Running);
+        machine.observe(NoProcess);
+        assert_eq!(machine.state(), State::Idle);
+        assert!(!machine.interrupted_game());
+    }
+
+    #[test]
+    fn lost_mid_match() {
+        let mut machine = run(&[Cs2Searching, AcceptFound, Clicked, Cs2Playing, GameLost]);
+        assert_eq!(machine.state(), State::Idle);
+        assert!(machine.interrupted_game());
+        machine.observe(Cs2Menu);
+        assert!(machine.interrupted_game());
+        machine.observe(Reconnected);
+        assert_eq!(machine.state(), State::InGame);
+        assert!(!machine.interrupted_game());
+    }
+
+    #[test]
+    fn left_mid_match() {
+        let mut machine = run(&[Cs2Searching, AcceptFound, Clicked, Cs2Playing, MatchLeft]);
+        assert_eq!(machine.state(), State::Cs2Running);
+        assert!(machine.interrupted_game());
+        // Still reported while the player looks around in the menu
+        machine.observe(MatchLeft);
+        assert!(machine.interrupted_game());
+        machine.observe(Cs2Searching);
+        assert!(!machine.interrupted_game());
+        machine.observe(MatchLeft);
+        assert!(!machine.interrupted_game());
+    }
+
+    #[test]
+    fn lost_outside_match() {
+        let machine = run(&[Cs2Idle, GameLost]);
+        assert_eq!(machine.state(), State::Idle);
+        assert!(!machine.interrupted_game());
+    }
+
+    #[test]
+    fn loading_keeps_in_game() {
+        // Popup and indicator are still up right after the click, then the map loads
+        let machine = run(&[
+            Cs2Searching,
+            AcceptFound,
+            Clicked,
+            Cs2Searching,
+            Cs2Idle,
+            Cs2Idle,
+        ]);
+        assert_eq!(machine.state(), State::InGame);
+    }
+
+    #[test]
+    fn popup_ignores_indicator() {
+        let mut machine = run(&[Cs2Searching, AcceptFound, Cs2Searching]);
+        assert_eq!(machine.state(), State::MatchFound);
+        machine.observe(HandledByUser);
+        machine.observe(Cs2Searching);
+        assert_eq!(machine.state(), State::Accepted);
+        machine.observe(Cs2Menu);
+        assert_eq!(machine.state(), State::Accepted);
+        // An older match left in the menu is still reported while this one loads
+        machine.observe(MatchLeft);
+        assert_eq!(machine.state(), State::Accepted);
+    }
+
+    #[test]
+    fn popup_times_out() {
+        let mut machine = run(&[Cs2Searching, AcceptFound, Clicked]);
+        machine.entered = Some(Instant::now() - POPUP_TIMEOUT);
+        machine.observe(Cs2Searching);
+        assert_eq!(machine.state(), State::Searching);
+    }
+}
@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::keyvalues::{self, Value};
+
+pub(crate) const CS2_APP_ID: &str = "730";
+const VIDEO_CONFIG_FILE: &str = "cs2_video.txt";
+// Default `installdir`, used if the app manifest can not be read
+const CS2_INSTALL_DIR: &str = "Counter-Strike Global Offensive";
+
+/// Existing Steam install directories in the usual places.
+pub(crate) fn steam_roots() -> Vec<PathBuf> {
+    let mut ret = vec![];
+
+    #[cfg(windows)]
+    {
+        if let Some(dir) = std::env::var_os("ProgramFiles(x86)") {
+            ret.push(PathBuf::from(dir).join("Steam"));
+        }
+        ret.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+    }
+
+    #[cfg(not(windows))]
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        ret.push(home.join(".steam/steam"));
+        ret.push(home.join(".local/share/Steam"));
+        ret.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+    }
+
+    ret.retain(|p| p.is_dir());
+    ret
+}
+
+/// Find `userdata/<id>/730/local/cfg/cs2_video.txt`, the most recently written one
+/// wins if there are several Steam accounts.
+pub(crate) fn find_cs2_video_config() -> Option<PathBuf> {
+    steam_roots()
+        .into_iter()
+        .filter_map(|root| std::fs::read_dir(root.join("userdata")).ok())
+        .flatten()
+        .flatten()
+        .map(|user| {
+            user.path()
+                .join(CS2_APP_ID)
+                .join("local")
+                .join("cfg")
+                .join(VIDEO_CONFIG_FILE)
+        })
+        .filter_map(|path| {
+            path.metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|t| (t, path))
+        })
+        .max_by_key(|(t, _)| *t)
+        .map(|(_, path)| path)
+}
+
+/// Library folders listed in `steamapps/libraryfolders.vdf`, with the apps installed there.
+fn library_folders(root: &Path) -> anyhow::Result<Vec<(PathBuf, Vec<String>)>> {
+    let content = std::fs::read_to_string(root.join("steamapps").join("libraryfolders.vdf"))?;
+    let vdf = keyvalues::parse(&content)?;
+    let folders = vdf
+        .get_section("libraryfolders")
+        .ok_or_else(|| anyhow!("Missing libraryfolders section"))?;
+
+    Ok(folders
+        .iter()
+        .filter_map(|(_, value)| match value {
+            Value::Section(folder) => Some(folder),
+            Value::String(_) => None,
+        })
+        .filter_map(|folder| {
+            let apps = folder
+                .get_section("apps")
+                .map(|apps| apps.iter().map(|(id, _)| id.to_string()).collect())
+                .unwrap_or_default();
+            Some((PathBuf::from(folder.get_str("path")?), apps))
+        })
+        .collect())
+}
+
+/// CS2 install directory, found through the Steam libraries
+pub(crate) fn find_cs2_install() -> Option<PathBuf> {
+    steam_roots()
+        .iter()
+        .filter_map(|root| {
+            library_folders(root)
+                .inspect_err(|e| log::debug!("Read library folders in {root:?} error: {e:?}"))
+                .ok()
+        })
+        .flatten()
+        .filter(|(_, apps)| apps.iter().any(|id| id == CS2_APP_ID))
+        .map(|(library, _)| {
+            let install_dir = std::fs::read_to_string(
+                library
+                    .join("steamapps")
+                    .join(format!("appmanifest_{CS2_APP_ID}.acf")),
+            )
+            .ok()
+            .and_then(|content| keyvalues::parse(&content).ok())
+            .and_then(|manifest| {
+                manifest
+                    .get_section("AppState")?
+                    .get_str("installdir")
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| CS2_INSTALL_DIR.to_string());
+            library.join("steamapps").join("common").join(install_dir)
+        })
+        .find(|path| path.is_dir())
+}
@@ -1,34 +1,18 @@
 use image::Rgb;
-use std::collections::HashMap;
-use sysinfo::{Pid, Process};
 
-use crate::{
-    CheckResult,
-    definitions::{PROCESS_5E_NAME, PROCESS_NAME},
-    matcher::Matcher,
-};
+use crate::{CheckResult, matcher::Matcher, process::ProcessWatcher};
 
-#[cfg(windows)]
-pub(crate) fn is_5e_foreground(title: &str) -> bool {
-    use winsafe::HWND;
-    HWND::GetForegroundWindow()
-        .and_then(|hwnd| hwnd.GetWindowText().ok())
-        .map(|t| t.contains(title))
-        .unwrap_or(false)
-}
-
-#[cfg(not(windows))]
-pub(crate) fn is_5e_foreground() -> bool {
-    false
+pub(crate) fn is_5e_foreground(pids: &[u32], title: &str) -> bool {
+    crate::window::is_foreground(pids, Some(title))
 }
 
 pub const MATCH_TEMPLATE: Matcher = Matcher::new(true, &[Rgb([72, 180, 30])], 90.0);
 
-pub(crate) fn check_need_handle(process: &HashMap<Pid, Process>) -> CheckResult {
-    let process_found = process.values().any(|x| x.name().eq(PROCESS_5E_NAME));
+pub(crate) fn check_need_handle(processes: &ProcessWatcher) -> CheckResult {
+    let process_found = processes.e5_running();
 
     if process_found {
-        let cs_found = process.values().any(|x| x.name().eq(PROCESS_NAME));
+        let cs_found = processes.cs2_running();
         if cs_found {
             return CheckResult::NoNeedProcess;
         }
@@ -1,9 +1,12 @@
-use std::collections::HashMap;
-
 use image::Rgb;
-use sysinfo::{Pid, Process};
 
-use crate::{CheckResult, definitions::PROCESS_NAME, matcher::Matcher, types::MatchOptions};
+use crate::{
+    CheckResult,
+    matcher::Matcher,
+    process::ProcessWatcher,
+    types::{MatchOptions, Region},
+    window::GameArea,
+};
 
 pub(crate) const MATCH_TEMPLATE: Matcher =
     Matcher::new(true, &[Rgb([52, 182, 81]), Rgb([58, 198, 90])], 20.0);
@@ -13,11 +16,15 @@ pub(crate) const LOBBY_MATCH_TEMPLATE: Matcher = Matcher::new(
     30.0,
 );
 
-pub(crate) fn check_primary_exec(process: &HashMap<Pid, Process>) -> anyhow::Result<CheckResult> {
-    if process.values().any(|x| x.name().eq(PROCESS_NAME)) {
+pub(crate) fn check_primary_exec(
+    processes: &ProcessWatcher,
+    area: &GameArea,
+) -> anyhow::Result<CheckResult> {
+    if processes.cs2_running() {
+        let point = area.point(Region::Transform(crate::tools::right_upon_side_in))?;
         //log::debug!("Check cs2 lobby");
         let ret = match crate::check_image_match(
-            crate::PointOption::Transform(crate::tools::get_right_upon_side),
+            point,
             false,
             &LOBBY_MATCH_TEMPLATE,
             MatchOptions::new(false, 4, 4),
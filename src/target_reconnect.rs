@@ -0,0 +1,51 @@
+use image::Rgb;
+
+use crate::{
+    SearchResult,
+    configure::ReconnectConfig,
+    matcher::Matcher,
+    state::StateMachine,
+    types::{MatchOptions, Region},
+    window::GameArea,
+};
+
+/// Reconnect button of the main menu
+pub(crate) const RECONNECT_TEMPLATE: Matcher =
+    Matcher::new(true, &[Rgb([76, 160, 72]), Rgb([88, 176, 82])], 20.0);
+
+pub(crate) const RECONNECT_OPTIONS: MatchOptions = MatchOptions::new(false, 12, 6);
+
+/// Only worth a look if CS2 went away in the middle of a match, the button is not there
+/// otherwise
+pub(crate) fn should_check(machine: &StateMachine) -> bool {
+    machine.interrupted_game()
+}
+
+pub(crate) fn check_reconnect(
+    area: &GameArea,
+    config: ReconnectConfig,
+) -> anyhow::Result<SearchResult> {
+    let point = area.point(Region::Geometry(config.region()))?;
+    crate::check_image_match(point, false, &RECONNECT_TEMPLATE, RECONNECT_OPTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(name: &str) -> SearchResult {
+        crate::search_fixture(name, &RECONNECT_TEMPLATE, RECONNECT_OPTIONS)
+    }
+
+    #[test]
+    fn finds_button() {
+        // Button spans (160, 38) to (340, 82), the first full block is its top left corner
+        assert_eq!(search("reconnect-button.png"), SearchResult::Found(166, 41));
+    }
+
+    #[test]
+    fn menu_without_button() {
+        // Green dot and bar are smaller than a block
+        assert_eq!(search("main-menu.png"), SearchResult::NotFound);
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use image::Rgb;
+
+use crate::{
+    SearchResult,
+    configure::RequeueConfig,
+    matcher::Matcher,
+    types::{MatchOptions, Region},
+    window::GameArea,
+};
+
+/// Red notice of the popup when a player failed to accept
+pub(crate) const FAILED_TEMPLATE: Matcher =
+    Matcher::new(true, &[Rgb([178, 42, 40]), Rgb([204, 56, 50])], 25.0);
+/// Lobby GO button
+pub(crate) const GO_TEMPLATE: Matcher =
+    Matcher::new(true, &[Rgb([93, 168, 78]), Rgb([82, 155, 68])], 20.0);
+
+/// Failed notice is looked for this long after the popup was last seen, the accept countdown
+/// has to run out first
+pub(crate) const FAILED_WINDOW: Duration = Duration::from_secs(30);
+
+pub(crate) const FAILED_OPTIONS: MatchOptions = MatchOptions::new(false, 8, 4);
+pub(crate) const GO_OPTIONS: MatchOptions = MatchOptions::new(false, 12, 8);
+
+/// Whether the failed accept notice is on the screen
+pub(crate) fn check_failed_accept(area: &GameArea, config: RequeueConfig) -> anyhow::Result<bool> {
+    let point = area.point(Region::Geometry(config.failed_region()))?;
+    let ret = crate::check_image_match(point, false, &FAILED_TEMPLATE, FAILED_OPTIONS)?;
+    Ok(matches!(ret, SearchResult::Found(_, _)))
+}
+
+pub(crate) fn find_go_button(
+    area: &GameArea,
+    config: RequeueConfig,
+) -> anyhow::Result<SearchResult> {
+    let point = area.point(Region::Geometry(config.go_region()))?;
+    crate::check_image_match(point, false, &GO_TEMPLATE, GO_OPTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(name: &str) -> SearchResult {
+        crate::search_fixture(name, &FAILED_TEMPLATE, FAILED_OPTIONS)
+    }
+
+    fn go(name: &str) -> SearchResult {
+        crate::search_fixture(name, &GO_TEMPLATE, GO_OPTIONS)
+    }
+
+    #[test]
+    fn failed_notice() {
+        // Notice spans (50, 80) to (450, 116)
+        assert_eq!(failed("failed-notice.png"), SearchResult::Found(54, 82));
+        assert_eq!(failed("accept-popup.png"), SearchResult::NotFound);
+        assert_eq!(failed("go-button.png"), SearchResult::NotFound);
+    }
+
+    #[test]
+    fn go_button() {
+        // Button spans (60, 30) to (200, 74), grey with a small green dot while searching
+        assert_eq!(go("go-button.png"), SearchResult::Found(66, 34));
+        assert_eq!(go("go-searching.png"), SearchResult::NotFound);
+    }
+
+    #[test]
+    fn loading_screen() {
+        // Brown roof and bush are near the template colors, the progress bar is GO green but
+        // thinner than a block
+        assert_eq!(failed("loading.png"), SearchResult::NotFound);
+        assert_eq!(go("loading.png"), SearchResult::NotFound);
+    }
+}
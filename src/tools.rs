@@ -1,12 +1,20 @@
-use std::{collections::HashSet, fs::OpenOptions, io::Write, thread::sleep, time::Duration};
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::Write,
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 use clap::parser::ValuesRef;
 use image::Rgb;
 use xcap::Monitor;
 
 use crate::{
-    EXIT_SIGNAL, PointOption, X_LIMIT, Y_LIMIT, match_algorithm, process_area, screen_cap,
-    types::{MatchOptions, Point},
+    EXIT_SIGNAL, PointOption, X_LIMIT, Y_LIMIT, match_algorithm,
+    process::ProcessWatcher,
+    process_area, screen_cap, target_reconnect, target_requeue,
+    types::{MatchOptions, Point, Region},
 };
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Default)]
@@ -121,45 +129,74 @@ pub(crate) fn test_image(file: &String, is_5e: bool, force_distance: bool) -> an
 }
 
 fn test_area(
+    config: &crate::configure::Configure,
     function: &str,
     force_distance: bool,
     save_image: bool,
     failed_only: bool,
 ) -> anyhow::Result<()> {
-    match function {
-        "cs2-lobby" => {
-            let opts = MatchOptions::new(force_distance, 2, 2);
-
-            let (point, area) = screen_cap(PointOption::Transform(get_right_upon_side), false)?;
-
-            let (buff, count) =
-                process_area(&area, &crate::target_main::LOBBY_MATCH_TEMPLATE, opts);
-            let early = count < 2 * 2;
-
-            let ret = if !early {
-                match match_algorithm(point, &buff, area.dimensions(), opts) {
-                    crate::SearchResult::Found(x, y) => {
-                        log::debug!("true {x} {y}");
-                        true
-                    }
-                    crate::SearchResult::NotFound => {
-                        log::debug!("false");
-                        false
-                    }
-                }
-            } else {
-                log::debug!("false {count}");
-                false
+    let (point, template, opts) = match function {
+        "cs2-lobby" => (
+            PointOption::Transform(get_right_upon_side),
+            &crate::target_main::LOBBY_MATCH_TEMPLATE,
+            MatchOptions::new(force_distance, 2, 2),
+        ),
+        "cs2-failed" | "cs2-go" | "cs2-reconnect" => {
+            let (geometry, template, opts) = match function {
+                "cs2-failed" => (
+                    config.requeue().failed_region(),
+                    &target_requeue::FAILED_TEMPLATE,
+                    target_requeue::FAILED_OPTIONS,
+                ),
+                "cs2-go" => (
+                    config.requeue().go_region(),
+                    &target_requeue::GO_TEMPLATE,
+                    target_requeue::GO_OPTIONS,
+                ),
+                _ => (
+                    config.reconnect().region(),
+                    &target_reconnect::RECONNECT_TEMPLATE,
+                    target_reconnect::RECONNECT_OPTIONS,
+                ),
             };
+            (
+                PointOption::Area(
+                    Point::from_monitor(&crate::primary_monitor()?)?,
+                    Region::Geometry(geometry),
+                ),
+                template,
+                MatchOptions::new(force_distance, opts.limit_x(), opts.limit_y()),
+            )
+        }
+        _ => unreachable!(),
+    };
 
-            if save_image && (!failed_only || !ret) {
-                area.save(format!(
-                    "{}-{ret}.png",
-                    timestamp_fmt("%Y-%m-%d_%H-%M-%S-%3f")
-                ))?;
+    let (point, area) = screen_cap(point, false)?;
+
+    let (buff, count) = process_area(&area, template, opts);
+    let early = count < opts.limit_x() * opts.limit_y();
+
+    let ret = if !early {
+        match match_algorithm(point, &buff, area.dimensions(), opts) {
+            crate::SearchResult::Found(x, y) => {
+                log::debug!("true {x} {y}");
+                true
+            }
+            crate::SearchResult::NotFound => {
+                log::debug!("false");
+                false
             }
         }
-        _ => unreachable!(),
+    } else {
+        log::debug!("false {count}");
+        false
+    };
+
+    if save_image && (!failed_only || !ret) {
+        area.save(format!(
+            "{}-{ret}.png",
+            timestamp_fmt("%Y-%m-%d_%H-%M-%S-%3f")
+        ))?;
     }
     Ok(())
 }
@@ -173,16 +210,70 @@ pub(crate) fn get_right_upon_side(monitor: Monitor) -> Point {
     )
 }
 
+pub(crate) fn right_upon_side_in(area: Point) -> Point {
+    let right = area.x() + area.width();
+    Point::new(right - 50, area.y(), right, area.y() + 20)
+}
+
+/// Regions of the configured `[requeue]` and `[reconnect]` sections are used
 pub(crate) fn continue_test_area(
+    config: &String,
     function: &str,
     force_distance: bool,
     save_image: bool,
     failed_only: bool,
     interval: u64,
 ) -> anyhow::Result<()> {
+    let config = crate::configure::Configure::load(config)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default();
     while EXIT_SIGNAL.get().is_none() {
-        test_area(function, force_distance, save_image, failed_only)?;
+        test_area(&config, function, force_distance, save_image, failed_only)?;
         sleep(Duration::from_millis(interval));
     }
     Ok(())
 }
+
+/// Compare the old `refresh_all` loop with `ProcessWatcher` on this machine
+pub(crate) fn measure_process_overhead(config: &String, rounds: u32) -> anyhow::Result<()> {
+    let config = crate::configure::Configure::load(config)
+        .inspect_err(|e| log::warn!("Failed to load config, using defaults: {e:#}"))
+        .unwrap_or_default();
+    let rounds = rounds.max(1);
+
+    let mut sys = sysinfo::System::new_all();
+    let instant = Instant::now();
+    for _ in 0..rounds {
+        sys.refresh_all();
+    }
+    let refresh_all = instant.elapsed() / rounds;
+
+    let mut watcher = ProcessWatcher::new();
+    let instant = Instant::now();
+    for _ in 0..rounds {
+        watcher.force_full_scan();
+        watcher.refresh(config.process());
+    }
+    let full_scan = instant.elapsed() / rounds;
+
+    println!("Processes: {}", sys.processes().len());
+    println!("refresh_all: {refresh_all:?}");
+    println!("Watcher full scan: {full_scan:?}");
+
+    // Without a target the watcher has no PID to re-check, it only waits for the next scan
+    if !watcher.cs2_running() && !watcher.e5_running() {
+        println!("Watcher cached: not measured, start CS2 or 5E first");
+        return Ok(());
+    }
+    let instant = Instant::now();
+    for _ in 0..rounds {
+        watcher.refresh(config.process());
+    }
+    let cached = instant.elapsed() / rounds;
+    println!(
+        "Watcher cached (CS2 {}, 5E {}): {cached:?}",
+        watcher.cs2_running(),
+        watcher.e5_running()
+    );
+    Ok(())
+}
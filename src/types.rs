@@ -1,7 +1,9 @@
 use serde::Deserialize;
 use xcap::Monitor;
 
-#[derive(Clone, Copy, Debug, Default, Deserialize)]
+use crate::window::GameWindow;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
 pub struct Point {
     pos1x: i32,
     pos1y: i32,
@@ -34,6 +36,197 @@ impl Point {
     pub const fn y(&self) -> i32 {
         self.pos1y
     }
+
+    /// Check `self` is a non-empty region fully inside `bounds`
+    pub const fn check_within(&self, bounds: &Self) -> Result<(), RegionError> {
+        if self.width() <= 0 || self.height() <= 0 {
+            return Err(RegionError::Empty(*self));
+        }
+        if self.pos1x < bounds.pos1x || self.pos1y < bounds.pos1y {
+            return Err(RegionError::Negative {
+                region: *self,
+                bounds: *bounds,
+            });
+        }
+        if self.pos2x > bounds.pos2x || self.pos2y > bounds.pos2y {
+            return Err(RegionError::OutOfBounds {
+                region: *self,
+                bounds: *bounds,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn from_monitor(monitor: &Monitor) -> anyhow::Result<Self> {
+        let x = monitor.x()?;
+        let y = monitor.y()?;
+        Ok(Self::new(
+            x,
+            y,
+            x + monitor.width()? as i32,
+            y + monitor.height()? as i32,
+        ))
+    }
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "({}, {})-({}, {})",
+            self.pos1x, self.pos1y, self.pos2x, self.pos2y
+        )
+    }
+}
+
+/// Region that can not be captured or matched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionError {
+    /// Width or height is not positive
+    Empty(Point),
+    /// Starts before the monitor or window, would wrap when casting to `u32`
+    Negative { region: Point, bounds: Point },
+    /// Ends after the monitor or window
+    OutOfBounds { region: Point, bounds: Point },
+    /// Smaller than the block the matcher looks for
+    TooSmall {
+        region: Point,
+        limit_x: usize,
+        limit_y: usize,
+    },
+}
+
+impl std::fmt::Display for RegionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty(region) => write!(f, "Region {region} is empty"),
+            Self::Negative { region, bounds } => {
+                write!(f, "Region {region} starts outside of {bounds}")
+            }
+            Self::OutOfBounds { region, bounds } => {
+                write!(f, "Region {region} ends outside of {bounds}")
+            }
+            Self::TooSmall {
+                region,
+                limit_x,
+                limit_y,
+            } => write!(
+                f,
+                "Region {region} is smaller than match block {limit_x}x{limit_y}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    const fn position(&self, area: &Point) -> (i32, i32) {
+        let left = area.x();
+        let top = area.y();
+        let right = left + area.width();
+        let bottom = top + area.height();
+        let mid_x = left + area.width() / 2;
+        let mid_y = top + area.height() / 2;
+
+        match self {
+            Self::Center => (mid_x, mid_y),
+            Self::Top => (mid_x, top),
+            Self::Bottom => (mid_x, bottom),
+            Self::Left => (left, mid_y),
+            Self::Right => (right, mid_y),
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Right,
+    Middle,
+}
+
+/// Region of `size` centered at `anchor` + `offset` of the game area
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Geometry {
+    #[serde(default)]
+    anchor: Anchor,
+    #[serde(default)]
+    offset: [i32; 2],
+    #[serde(default = "Geometry::default_size")]
+    size: [i32; 2],
+}
+
+impl Geometry {
+    pub const fn new(anchor: Anchor, offset: [i32; 2], size: [i32; 2]) -> Self {
+        Self {
+            anchor,
+            offset,
+            size,
+        }
+    }
+
+    fn default_size() -> [i32; 2] {
+        [400, 200]
+    }
+
+    pub fn default_cs2() -> Self {
+        Self::new(Anchor::Center, [0, -100], Self::default_size())
+    }
+
+    pub fn default_5e() -> Self {
+        Self::new(Anchor::Center, [0, 50], Self::default_size())
+    }
+
+    /// Notice shown in place of the accept popup when someone did not accept
+    pub fn default_cs2_failed() -> Self {
+        Self::new(Anchor::Center, [0, -100], [500, 200])
+    }
+
+    /// Reconnect button shown in the main menu while a match is still running
+    pub fn default_cs2_reconnect() -> Self {
+        Self::new(Anchor::Top, [0, 90], [500, 120])
+    }
+
+    /// Elapsed search time next to the lobby searching indicator
+    pub fn default_cs2_timer() -> Self {
+        Self::new(Anchor::TopRight, [-60, 10], [120, 20])
+    }
+
+    /// GO button in the lower right corner of the play menu
+    pub fn default_cs2_go() -> Self {
+        Self::new(Anchor::BottomRight, [-150, -60], [240, 100])
+    }
+
+    pub fn region(&self, area: Point) -> Point {
+        let (x, y) = self.anchor.position(&area);
+        let [width, height] = self.size;
+        let left = x + self.offset[0] - width / 2;
+        let top = y + self.offset[1] - height / 2;
+
+        Point::new(left, top, left + width, top + height)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -44,7 +237,7 @@ pub(crate) struct MatchOptions {
 }
 
 impl MatchOptions {
-    pub(crate) fn new(force_distance: bool, limit_x: usize, limit_y: usize) -> Self {
+    pub(crate) const fn new(force_distance: bool, limit_x: usize, limit_y: usize) -> Self {
         Self {
             force_distance,
             limit_x,
@@ -65,9 +258,30 @@ impl MatchOptions {
     }
 }
 
+/// How to find the region inside the game area
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Region {
+    Geometry(Geometry),
+    Transform(fn(Point) -> Point),
+}
+
+impl Region {
+    pub(crate) fn apply(&self, area: Point) -> Point {
+        match self {
+            Self::Geometry(geometry) => geometry.region(area),
+            Self::Transform(func) => func(area),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub(crate) enum PointOption {
     Some(Point),
     Transform(fn(Monitor) -> Point),
+    // Game area and the region inside it
+    Area(Point, Region),
+    // Capture from the window instead of the monitor
+    Window(GameWindow, Region),
     None,
 }
 
@@ -0,0 +1,177 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+use xcap::Monitor;
+
+use crate::{CS2_VIDEO_CFG, configure::VideoLocator, keyvalues, steam, types::Point};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DisplayMode {
+    Windowed,
+    Fullscreen,
+    Borderless,
+}
+
+/// Game resolution read from `cs2_video.txt`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct VideoConfig {
+    width: i32,
+    height: i32,
+    mode: DisplayMode,
+}
+
+impl VideoConfig {
+    pub(crate) fn parse(content: &str) -> anyhow::Result<Self> {
+        let root = keyvalues::parse(content)?;
+        let section = root
+            .iter()
+            .find_map(|(_, v)| match v {
+                keyvalues::Value::Section(s) => Some(s),
+                keyvalues::Value::String(_) => None,
+            })
+            .ok_or_else(|| anyhow!("Missing video config section"))?;
+
+        let get = |key: &str| -> anyhow::Result<i32> {
+            section
+                .get_str(key)
+                .ok_or_else(|| anyhow!("Missing {key:?}"))?
+                .parse()
+                .with_context(|| format!("Parse {key:?}"))
+        };
+
+        let width = get("setting.defaultres")?;
+        let height = get("setting.defaultresheight")?;
+        if width <= 0 || height <= 0 {
+            return Err(anyhow!("Invalid resolution {width}x{height}"));
+        }
+        let mode = if get("setting.fullscreen")? != 0 {
+            DisplayMode::Fullscreen
+        } else if get("setting.nowindowborder").unwrap_or_default() != 0 {
+            DisplayMode::Borderless
+        } else {
+            DisplayMode::Windowed
+        };
+
+        Ok(Self {
+            width,
+            height,
+            mode,
+        })
+    }
+
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&read_to_string(path)?)
+    }
+
+    pub(crate) fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub(crate) fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub(crate) fn mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    /// Area the game draws into, in the same coordinates as `Point::from_monitor`.
+    ///
+    /// Fullscreen switches the monitor to the game resolution, a windowed game
+    /// is centered by default and a borderless window always covers the whole monitor.
+    pub(crate) fn game_area(&self, monitor: &Monitor) -> anyhow::Result<Point> {
+        let x = monitor.x()?;
+        let y = monitor.y()?;
+        let monitor_width = monitor.width()? as i32;
+        let monitor_height = monitor.height()? as i32;
+
+        Ok(match self.mode {
+            DisplayMode::Fullscreen => Point::new(x, y, x + self.width, y + self.height),
+            DisplayMode::Borderless => Point::new(x, y, x + monitor_width, y + monitor_height),
+            DisplayMode::Windowed => {
+                let left = x + (monitor_width - self.width) / 2;
+                let top = y + (monitor_height - self.height) / 2;
+                Point::new(left, top, left + self.width, top + self.height)
+            }
+        })
+    }
+}
+
+/// Load the video config from the command line override, the configured path
+/// or the Steam userdata directory, in that order.
+pub(crate) fn resolve(locator: &VideoLocator) -> Option<VideoConfig> {
+    let path = match CS2_VIDEO_CFG.get() {
+        Some(path) => PathBuf::from(path),
+        None if locator.enabled() => match locator.path() {
+            Some(path) => PathBuf::from(path),
+            None => steam::find_cs2_video_config().or_else(|| {
+                log::warn!("Unable to locate cs2_video.txt, fallback to monitor size");
+                None
+            })?,
+        },
+        None => return None,
+    };
+
+    VideoConfig::load(&path)
+        .inspect(|video| {
+            log::info!(
+                "Load {}: {}x{} {:?}",
+                path.display(),
+                video.width(),
+                video.height(),
+                video.mode()
+            )
+        })
+        .inspect_err(|e| log::warn!("Failed to load {}: {e:#}", path.display()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(fullscreen: u8, borderless: u8) -> String {
+        format!(
+            r#""video.cfg"
+            {{
+                "setting.defaultres"        "1280"
+                "setting.defaultresheight"  "720"
+                "setting.fullscreen"        "{fullscreen}"
+                "setting.nowindowborder"    "{borderless}"
+            }}"#
+        )
+    }
+
+    #[test]
+    fn cs2_video_sample() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/video/cs2_video.txt"
+        );
+        let config = VideoConfig::load(Path::new(path)).unwrap();
+        assert_eq!((config.width(), config.height()), (2560, 1440));
+        // Fullscreen wins over the border setting
+        assert_eq!(config.mode(), DisplayMode::Fullscreen);
+    }
+
+    #[test]
+    fn display_mode() {
+        let mode = |fullscreen, borderless| VideoConfig::parse(&video(fullscreen, borderless));
+        assert_eq!(mode(0, 0).unwrap().mode(), DisplayMode::Windowed);
+        assert_eq!(mode(0, 1).unwrap().mode(), DisplayMode::Borderless);
+        assert_eq!(mode(1, 0).unwrap().mode(), DisplayMode::Fullscreen);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(VideoConfig::parse("").is_err());
+        assert!(VideoConfig::parse(r#""video.cfg" { "setting.defaultres" "1280" }"#).is_err());
+        let zero = video(0, 0).replace("\"720\"", "\"0\"");
+        assert!(VideoConfig::parse(&zero).is_err());
+        let text = video(0, 0).replace("\"1280\"", "\"wide\"");
+        assert!(VideoConfig::parse(&text).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+use xcap::Window;
+
+use crate::{
+    primary_monitor,
+    types::{Point, PointOption, Region},
+    video_config::VideoConfig,
+};
+
+/// Top level window of a target process
+#[derive(Clone, Debug)]
+pub(crate) struct GameWindow {
+    inner: Window,
+    // Client area in screen coordinates
+    client: Point,
+}
+
+impl GameWindow {
+    /// Find a visible window owned by one of `pids`, or else one titled exactly `title`.
+    /// Any window above the game may mention it, so the PID is looked for first
+    pub(crate) fn find(pids: &[u32], title: Option<&str>) -> Option<Self> {
+        let windows = Window::all()
+            .inspect_err(|e| log::warn!("List windows error: {e:?}"))
+            .ok()?;
+
+        let visible = windows
+            .into_iter()
+            .filter(|w| !w.is_minimized().unwrap_or(true))
+            .filter(|w| w.width().unwrap_or_default() > 0 && w.height().unwrap_or_default() > 0)
+            .collect::<Vec<_>>();
+        visible
+            .iter()
+            .find(|w| w.pid().is_ok_and(|pid| pids.contains(&pid)))
+            .or_else(|| {
+                visible
+                    .iter()
+                    .find(|w| title.is_some_and(|title| w.title().is_ok_and(|t| t == title)))
+            })
+            .and_then(|w| {
+                let client = client_area(w)
+                    .inspect_err(|e| log::warn!("Get window client area error: {e:?}"))
+                    .ok()?;
+                Some(Self {
+                    inner: w.clone(),
+                    client,
+                })
+            })
+    }
+
+    pub(crate) fn client(&self) -> Point {
+        self.client
+    }
+
+    /// Capture `region` (screen coordinates, inside the client area) from the window
+    pub(crate) fn capture(&self, region: Point) -> anyhow::Result<RgbaImage> {
+        region.check_within(&self.client)?;
+        let image = self.inner.capture_image()?;
+        let x = region.x() - self.inner.x()?;
+        let y = region.y() - self.inner.y()?;
+
+        Ok(image::imageops::crop_imm(
+            &image,
+            x as u32,
+            y as u32,
+            region.width() as u32,
+            region.height() as u32,
+        )
+        .to_image())
+    }
+}
+
+#[cfg(windows)]
+fn client_area(window: &Window) -> anyhow::Result<Point> {
+    use winsafe::{HWND, POINT};
+
+    let hwnd = unsafe { HWND::from_ptr(window.id()? as usize as _) };
+    let rect = hwnd.GetClientRect()?;
+    let origin = hwnd.ClientToScreen(POINT::default())?;
+    Ok(Point::new(
+        origin.x,
+        origin.y,
+        origin.x + rect.right - rect.left,
+        origin.y + rect.bottom - rect.top,
+    ))
+}
+
+// X11 window geometry does not include the decorations drawn by the window manager
+#[cfg(not(windows))]
+fn client_area(window: &Window) -> anyhow::Result<Point> {
+    let x = window.x()?;
+    let y = window.y()?;
+    Ok(Point::new(
+        x,
+        y,
+        x + window.width()? as i32,
+        y + window.height()? as i32,
+    ))
+}
+
+/// Focused top level window
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Foreground {
+    // HWND on Windows, X11 window id otherwise
+    id: usize,
+    title: String,
+    pid: Option<u32>,
+}
+
+impl Foreground {
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl std::fmt::Display for Foreground {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.pid {
+            Some(pid) => write!(f, "{:?} (pid {pid})", self.title),
+            None => write!(f, "{:?}", self.title),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn foreground() -> anyhow::Result<Option<Foreground>> {
+    use winsafe::HWND;
+
+    let Some(hwnd) = HWND::GetForegroundWindow() else {
+        return Ok(None);
+    };
+    let (_, pid) = hwnd.GetWindowThreadProcessId();
+    Ok(Some(Foreground {
+        id: hwnd.ptr() as usize,
+        title: hwnd.GetWindowText()?,
+        pid: (pid != 0).then_some(pid),
+    }))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn foreground() -> anyhow::Result<Option<Foreground>> {
+    let x11 = crate::x11::get()?;
+    let Some(window) = x11.active_window()? else {
+        return Ok(None);
+    };
+    Ok(Some(Foreground {
+        id: window as usize,
+        title: x11.window_title(window)?.unwrap_or_default(),
+        pid: x11.window_pid(window)?,
+    }))
+}
+
+#[cfg(windows)]
+fn activate(id: usize) -> anyhow::Result<()> {
+    use winsafe::HWND;
+
+    let hwnd = unsafe { HWND::from_ptr(id as _) };
+    if hwnd.IsIconic() {
+        hwnd.ShowWindow(winsafe::co::SW::RESTORE);
+    }
+    if !hwnd.SetForegroundWindow() {
+        return Err(anyhow::anyhow!("SetForegroundWindow({id:#x}) is refused"));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn activate(id: usize) -> anyhow::Result<()> {
+    crate::x11::get()?.activate(u32::try_from(id)?)
+}
+
+/// Activate window `id` and wait until it actually has focus
+pub(crate) fn focus(id: usize, timeout: Duration) -> anyhow::Result<()> {
+    activate(id)?;
+    let instant = Instant::now();
+    while instant.elapsed() < timeout {
+        if foreground()?.is_some_and(|window| window.id == id) {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    Err(anyhow::anyhow!(
+        "Window {id:#x} did not get focus in {timeout:?}"
+    ))
+}
+
+/// Focus the target window, returns the window that had focus before if it was another one
+pub(crate) fn bring_to_front(
+    pids: &[u32],
+    title: &str,
+    timeout: Duration,
+) -> anyhow::Result<Option<Foreground>> {
+    // Minimized windows count here, activating restores them. A title is only trusted when
+    // it is exact, a browser tab or an editor may mention the game
+    let windows = Window::all()?;
+    let id = windows
+        .iter()
+        .find(|w| w.pid().is_ok_and(|pid| pids.contains(&pid)))
+        .or_else(|| windows.iter().find(|w| w.title().is_ok_and(|t| t == title)))
+        .ok_or_else(|| anyhow::anyhow!("Target window is not found"))?
+        .id()? as usize;
+    let previous = foreground()?;
+    if previous.as_ref().is_some_and(|window| window.id == id) {
+        return Ok(None);
+    }
+    focus(id, timeout)?;
+    Ok(previous)
+}
+
+/// Whether the focused window is owned by one of `pids`, or its title is exactly `title`.
+/// A browser tab or an editor may mention the game, so a partial title does not count
+pub(crate) fn is_foreground(pids: &[u32], title: Option<&str>) -> bool {
+    match foreground() {
+        Ok(Some(window)) => {
+            window.pid.is_some_and(|pid| pids.contains(&pid))
+                || title.is_some_and(|title| window.title == title)
+        }
+        Ok(None) => false,
+        Err(e) => {
+            log::debug!("Get foreground window error: {e:?}");
+            false
+        }
+    }
+}
+
+/// Print the focused window whenever it changes
+pub(crate) fn display_foreground() -> anyhow::Result<()> {
+    let mut last = None;
+    while crate::EXIT_SIGNAL.get().is_none() {
+        let current = foreground()?.map(|window| window.to_string());
+        if current != last {
+            println!("{}", current.as_deref().unwrap_or("<none>"));
+            last = current;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+/// Where a target draws, every region is computed inside of it
+#[derive(Clone, Debug)]
+pub(crate) enum GameArea {
+    Monitor,
+    Video(VideoConfig),
+    Window(GameWindow),
+}
+
+impl GameArea {
+    pub(crate) fn detect(
+        window_capture: bool,
+        pids: &[u32],
+        title: Option<&str>,
+        video: Option<VideoConfig>,
+    ) -> Self {
+        if window_capture {
+            match GameWindow::find(pids, title) {
+                Some(window) => return Self::Window(window),
+                None => log::trace!("Window not found, fallback to monitor capture"),
+            }
+        }
+        video.map(Self::Video).unwrap_or(Self::Monitor)
+    }
+
+    pub(crate) fn point(&self, region: Region) -> anyhow::Result<PointOption> {
+        Ok(match self {
+            Self::Monitor => PointOption::Area(Point::from_monitor(&primary_monitor()?)?, region),
+            Self::Video(video) => PointOption::Area(video.game_area(&primary_monitor()?)?, region),
+            Self::Window(window) => PointOption::Window(window.clone(), region),
+        })
+    }
+}
@@ -0,0 +1,222 @@
+//! EWMH queries over a shared X11 connection.
+//!
+//! Only works with an X server (or XWayland for X11 clients), `DISPLAY` selects the server.
+
+use std::sync::OnceLock;
+
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{
+        Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, GetPropertyReply, Window,
+    },
+    rust_connection::RustConnection,
+};
+
+static CONNECTION: OnceLock<X11> = OnceLock::new();
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        UTF8_STRING,
+    }
+}
+
+pub(crate) struct X11 {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+impl X11 {
+    fn connect() -> anyhow::Result<Self> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        Ok(Self { conn, root, atoms })
+    }
+
+    fn property(
+        &self,
+        window: Window,
+        property: Atom,
+        r#type: impl Into<Atom>,
+    ) -> anyhow::Result<GetPropertyReply> {
+        Ok(self
+            .conn
+            .get_property(false, window, property, r#type, 0, u32::MAX)?
+            .reply()?)
+    }
+
+    pub(crate) fn active_window(&self) -> anyhow::Result<Option<Window>> {
+        let reply = self.property(self.root, self.atoms._NET_ACTIVE_WINDOW, AtomEnum::WINDOW)?;
+        Ok(reply
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|window| *window != x11rb::NONE))
+    }
+
+    /// `_NET_WM_NAME`, or `WM_NAME` if the client does not set it
+    pub(crate) fn window_title(&self, window: Window) -> anyhow::Result<Option<String>> {
+        let reply = self.property(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)?;
+        let reply = match reply.value.is_empty() {
+            true => self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING)?,
+            false => reply,
+        };
+        Ok((!reply.value.is_empty()).then(|| String::from_utf8_lossy(&reply.value).into_owned()))
+    }
+
+    /// Ask the window manager to focus and raise `window`, does not wait for it
+    pub(crate) fn activate(&self, window: Window) -> anyhow::Result<()> {
+        // Source indication 2: request from a pager, some window managers ignore normal applications
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            self.atoms._NET_ACTIVE_WINDOW,
+            [2, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        self.conn.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn window_pid(&self, window: Window) -> anyhow::Result<Option<u32>> {
+        let reply = self.property(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL)?;
+        Ok(reply.value32().and_then(|mut value| value.next()))
+    }
+}
+
+/// Connect on first use, the connection is kept for the whole run
+pub(crate) fn get() -> anyhow::Result<&'static X11> {
+    if let Some(x11) = CONNECTION.get() {
+        return Ok(x11);
+    }
+    let x11 = X11::connect()?;
+    Ok(CONNECTION.get_or_init(|| x11))
+}
+
+#[cfg(test)]
+mod tests {
+    use x11rb::{
+        protocol::xproto::{CreateWindowAux, PropMode, WindowClass},
+        wrapper::ConnectionExt as _,
+    };
+
+    use super::*;
+
+    /// Unmapped window with the given properties, `None` leaves one out
+    fn create_window(x11: &X11, net_name: Option<&str>, name: &str, pid: Option<u32>) -> Window {
+        let conn = &x11.conn;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            x11.root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        if let Some(net_name) = net_name {
+            conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                x11.atoms._NET_WM_NAME,
+                x11.atoms.UTF8_STRING,
+                net_name.as_bytes(),
+            )
+            .unwrap();
+        }
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            name.as_bytes(),
+        )
+        .unwrap();
+        if let Some(pid) = pid {
+            conn.change_property32(
+                PropMode::REPLACE,
+                window,
+                x11.atoms._NET_WM_PID,
+                AtomEnum::CARDINAL,
+                &[pid],
+            )
+            .unwrap();
+        }
+        window
+    }
+
+    /// Xvfb runs no window manager, set what it would
+    fn set_active(x11: &X11, window: Window) {
+        x11.conn
+            .change_property32(
+                PropMode::REPLACE,
+                x11.root,
+                x11.atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                &[window],
+            )
+            .unwrap();
+        x11.conn.flush().unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs an X server, run under xvfb-run with --ignored"]
+    fn foreground_window() {
+        let x11 = get().unwrap();
+        let game = create_window(x11, Some("Counter-Strike 2"), "cs2", Some(4242));
+        let legacy = create_window(x11, None, "5EClient", None);
+        let browser = create_window(x11, Some("Counter-Strike 2 - Wiki"), "browser", Some(77));
+
+        set_active(x11, x11rb::NONE);
+        assert_eq!(x11.active_window().unwrap(), None);
+        assert!(!crate::window::is_foreground(
+            &[4242],
+            Some("Counter-Strike 2")
+        ));
+
+        set_active(x11, game);
+        assert_eq!(x11.active_window().unwrap(), Some(game));
+        assert_eq!(
+            x11.window_title(game).unwrap().as_deref(),
+            Some("Counter-Strike 2")
+        );
+        assert_eq!(x11.window_pid(game).unwrap(), Some(4242));
+        assert!(crate::window::is_foreground(&[4242], None));
+        assert!(!crate::window::is_foreground(&[1], None));
+        assert!(crate::window::is_foreground(&[1], Some("Counter-Strike 2")));
+
+        // Falls back to WM_NAME, no PID to match
+        set_active(x11, legacy);
+        assert_eq!(
+            x11.window_title(legacy).unwrap().as_deref(),
+            Some("5EClient")
+        );
+        assert_eq!(x11.window_pid(legacy).unwrap(), None);
+        assert!(!crate::window::is_foreground(
+            &[4242],
+            Some("Counter-Strike 2")
+        ));
+        assert!(crate::window::is_foreground(&[], Some("5EClient")));
+
+        // Only mentions the game
+        set_active(x11, browser);
+        assert!(!crate::window::is_foreground(
+            &[4242],
+            Some("Counter-Strike 2")
+        ));
+    }
+}